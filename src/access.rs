@@ -0,0 +1,553 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::action::{ActionMap, InputBinding};
+use crate::event::{InputEvent, KeyAction, KeyMods};
+use crate::input::{InputState, KMK};
+
+// Key repeat run by the engine instead of the OS.
+// A key counts as held once it has been down for delay, and
+// repeats every interval after that. While this is set on an
+// InputState the OS's own Repeat events are ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RepeatSettings
+{
+    pub delay: Duration,
+    pub interval: Duration,
+}
+
+// Sticky modifiers, like the OS accessibility option.
+// Tapping a modifier on its own applies it to the next key or
+// button press. Tapping it again locks it on until a third tap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StickyModifiers
+{
+    pub enabled: bool,
+    pub latched: KeyMods,
+    pub locked: KeyMods,
+    // The modifier that went down with nothing pressed since
+    tapping: Option<KMK>,
+}
+
+// Per-action hold-to-toggle. A toggled action turns on with one
+// press of its input and off with the next, instead of needing
+// the input held down the whole time.
+pub struct ActionToggles
+{
+    pub actions: HashSet<String>,
+    active: HashMap<String, bool>,
+    was_pressed: HashMap<String, bool>,
+}
+
+// Per-action repeat, for actions that want a different delay or rate
+// than InputState::repeat gives every key, like a slower menu scroll.
+// Timing starts when the action is first pressed, by whichever input.
+pub struct ActionRepeats
+{
+    pub actions: HashMap<String, RepeatSettings>,
+    pressed_at: HashMap<String, Instant>,
+    counts: HashMap<String, u32>,
+    repeated: HashSet<String>,
+}
+
+// One-switch scanning. The highlight steps through items on its own
+// and the player presses their single switch to pick the highlighted
+// one. The hooks are how menus find out what to draw and do.
+pub struct SwitchScanner
+{
+    pub items: usize,
+    pub interval: Duration,
+    pub switch: InputBinding,
+    pub current: usize,
+    pub on_highlight: Option<Box<dyn FnMut(usize)>>,
+    pub on_select: Option<Box<dyn FnMut(usize)>>,
+    last_step: Option<Instant>,
+    was_pressed: bool,
+}
+
+fn modifierBit(key: KMK) -> KeyMods
+{
+    return match key
+    {
+        KMK::LShift | KMK::RShift =>        KeyMods::SHIFT,
+        KMK::LControl | KMK::RControl =>    KeyMods::CONTROL,
+        KMK::LAlt | KMK::RAlt =>            KeyMods::ALT,
+        KMK::LSuper | KMK::RSuper =>        KeyMods::SUPER,
+    };
+}
+
+impl RepeatSettings
+{
+    // rate is in repeats per second
+    pub fn fromRate(delay: Duration, rate: u32) -> RepeatSettings
+    {
+        RepeatSettings
+        {
+            delay,
+            interval: Duration::from_secs(1) / rate.max(1),
+        }
+    }
+
+    // How many repeats a key down for this long has had
+    pub fn repeatsAfter(&self, down: Duration) -> u32
+    {
+        if down < self.delay
+        {
+            return 0;
+        }
+        return 1 + ((down - self.delay).as_nanos() / self.interval.as_nanos().max(1)) as u32;
+    }
+}
+
+impl StickyModifiers
+{
+    pub fn new() -> StickyModifiers
+    {
+        StickyModifiers
+        {
+            enabled: false,
+            latched: KeyMods::NONE,
+            locked: KeyMods::NONE,
+            tapping: None,
+        }
+    }
+
+    // Drops any latched, locked or half tapped modifiers, leaving
+    // the feature itself on or off
+    pub fn reset(&mut self)
+    {
+        self.latched = KeyMods::NONE;
+        self.locked = KeyMods::NONE;
+        self.tapping = None;
+    }
+
+    // Adds any latched or locked modifiers to key and button presses
+    pub fn filter(&mut self, event: InputEvent) -> InputEvent
+    {
+        return match event
+        {
+            InputEvent::Modifier(key, KeyAction::Press) =>
+            {
+                self.tapping = Some(key);
+                event
+            },
+            InputEvent::Modifier(key, KeyAction::Release) =>
+            {
+                if self.tapping == Some(key)
+                {
+                    let bit = modifierBit(key);
+                    if self.locked.contains(bit)
+                    {
+                        self.locked.bits &= !bit.bits;
+                    }
+                    else if self.latched.contains(bit)
+                    {
+                        self.latched.bits &= !bit.bits;
+                        self.locked = self.locked | bit;
+                    }
+                    else
+                    {
+                        self.latched = self.latched | bit;
+                    }
+                }
+                self.tapping = None;
+                event
+            },
+            InputEvent::Key(key, KeyAction::Press, mods) =>
+            {
+                let mods = mods | self.latched | self.locked;
+                self.latched = KeyMods::NONE;
+                self.tapping = None;
+                InputEvent::Key(key, KeyAction::Press, mods)
+            },
+            InputEvent::MouseButton(button, KeyAction::Press, mods) =>
+            {
+                let mods = mods | self.latched | self.locked;
+                self.latched = KeyMods::NONE;
+                self.tapping = None;
+                InputEvent::MouseButton(button, KeyAction::Press, mods)
+            },
+            _ => event,
+        };
+    }
+}
+
+impl ActionToggles
+{
+    pub fn new() -> ActionToggles
+    {
+        ActionToggles
+        {
+            actions: HashSet::new(),
+            active: HashMap::new(),
+            was_pressed: HashMap::new(),
+        }
+    }
+
+    pub fn setToggle<A: AsRef<str>>(&mut self, action: A, toggle: bool)
+    {
+        if toggle
+        {
+            self.actions.insert(action.as_ref().to_string());
+        }
+        else
+        {
+            self.actions.remove(action.as_ref());
+            self.active.remove(action.as_ref());
+        }
+    }
+
+    // Call once a frame after input has been handled
+    pub fn update(&mut self, actions: &ActionMap, inState: &InputState)
+    {
+        for action in &self.actions
+        {
+            let pressed = actions.isActionPressed(action, inState);
+            let wasPressed = self.was_pressed.insert(action.clone(), pressed).unwrap_or(false);
+            if pressed && !wasPressed
+            {
+                let active = self.active.entry(action.clone()).or_insert(false);
+                *active = !*active;
+            }
+        }
+    }
+
+    // Use in place of ActionMap::isActionPressed for actions that might be toggled
+    pub fn isActionActive<A: AsRef<str>>(&self, action: A, actions: &ActionMap, inState: &InputState) -> bool
+    {
+        if self.actions.contains(action.as_ref())
+        {
+            return self.active.get(action.as_ref()).cloned().unwrap_or(false);
+        }
+        return actions.isActionPressed(action, inState);
+    }
+}
+
+impl ActionRepeats
+{
+    pub fn new() -> ActionRepeats
+    {
+        ActionRepeats
+        {
+            actions: HashMap::new(),
+            pressed_at: HashMap::new(),
+            counts: HashMap::new(),
+            repeated: HashSet::new(),
+        }
+    }
+
+    // None goes back to the action following its inputs
+    pub fn setRepeat<A: AsRef<str>>(&mut self, action: A, settings: Option<RepeatSettings>)
+    {
+        match settings
+        {
+            Some(settings) => {self.actions.insert(action.as_ref().to_string(), settings);},
+            None =>
+            {
+                self.actions.remove(action.as_ref());
+                self.pressed_at.remove(action.as_ref());
+                self.counts.remove(action.as_ref());
+            },
+        }
+    }
+
+    // Call once a frame after input has been handled
+    pub fn update(&mut self, actions: &ActionMap, inState: &InputState)
+    {
+        self.repeated.clear();
+        for (action, settings) in &self.actions
+        {
+            if !actions.isActionPressed(action, inState)
+            {
+                self.pressed_at.remove(action);
+                self.counts.remove(action);
+                continue;
+            }
+
+            let pressedAt = *self.pressed_at.entry(action.clone()).or_insert(inState.frame_time);
+            let repeats = settings.repeatsAfter(inState.frame_time.saturating_duration_since(pressedAt));
+            let count = self.counts.entry(action.clone()).or_insert(0);
+            if repeats > *count
+            {
+                *count = repeats;
+                self.repeated.insert(action.clone());
+            }
+        }
+    }
+
+    // Use in place of ActionMap::isActionHeld for actions that might have their own repeat
+    pub fn isActionHeld<A: AsRef<str>>(&self, action: A, actions: &ActionMap, inState: &InputState) -> bool
+    {
+        if self.actions.contains_key(action.as_ref())
+        {
+            return self.counts.get(action.as_ref()).map(|count| *count > 0).unwrap_or(false);
+        }
+        return actions.isActionHeld(action, inState);
+    }
+
+    // True on frames where the action repeats under its own settings
+    pub fn isActionRepeated<A: AsRef<str>>(&self, action: A) -> bool
+    {
+        return self.repeated.contains(action.as_ref());
+    }
+}
+
+impl SwitchScanner
+{
+    pub fn new(items: usize, interval: Duration, switch: InputBinding) -> SwitchScanner
+    {
+        SwitchScanner
+        {
+            items,
+            interval,
+            switch,
+            current: 0,
+            on_highlight: None,
+            on_select: None,
+            last_step: None,
+            was_pressed: false,
+        }
+    }
+
+    // Call once a frame. Steps the highlight when interval has passed
+    // and selects when the switch goes down.
+    pub fn update(&mut self, now: Instant, inState: &InputState)
+    {
+        if self.items == 0
+        {
+            return;
+        }
+
+        let pressed = self.switch.isPressed(inState);
+        if pressed && !self.was_pressed
+        {
+            if let Some(hook) = self.on_select.as_mut()
+            {
+                hook(self.current);
+            }
+            // Start the wait over so the player sees where they landed
+            self.last_step = Some(now);
+        }
+        self.was_pressed = pressed;
+
+        match self.last_step
+        {
+            Some(last) if now.duration_since(last) >= self.interval =>
+            {
+                self.current = (self.current + 1) % self.items;
+                self.last_step = Some(now);
+                if let Some(hook) = self.on_highlight.as_mut()
+                {
+                    hook(self.current);
+                }
+            },
+            Some(_) => {},
+            None =>
+            {
+                self.last_step = Some(now);
+                if let Some(hook) = self.on_highlight.as_mut()
+                {
+                    hook(self.current);
+                }
+            },
+        }
+    }
+}
+
+/*************************************/
+// Access tests
+
+#[test]
+fn stickyModifierTest()
+{
+    use crate::input::KSK;
+
+    let mut inpState = InputState::new();
+    inpState.sticky.enabled = true;
+
+    inpState.injectModifier(KMK::LControl, KeyAction::Press);
+    inpState.injectModifier(KMK::LControl, KeyAction::Release);
+    inpState.injectKey(KSK::S, KeyAction::Press);
+
+    assert_eq!(inpState.isComboPressed(KSK::S as u64, KeyMods::CONTROL), true);
+
+    // Used up by the S
+    inpState.injectKey(KSK::D, KeyAction::Press);
+
+    assert_eq!(inpState.isComboPressed(KSK::D as u64, KeyMods::NONE), true);
+
+    // Two taps lock it on
+    inpState.injectModifier(KMK::RShift, KeyAction::Press);
+    inpState.injectModifier(KMK::RShift, KeyAction::Release);
+    inpState.injectModifier(KMK::RShift, KeyAction::Press);
+    inpState.injectModifier(KMK::RShift, KeyAction::Release);
+    inpState.injectKey(KSK::A, KeyAction::Press);
+    inpState.injectKey(KSK::B, KeyAction::Press);
+
+    assert_eq!(inpState.isComboPressed(KSK::B as u64, KeyMods::SHIFT), true);
+}
+
+#[test]
+fn engineRepeatTest()
+{
+    use crate::input::KSK;
+
+    let now = Instant::now();
+    let mut inpState = InputState::new();
+    inpState.repeat = Some(RepeatSettings::fromRate(Duration::from_millis(500), 10));
+
+    inpState.beginFrame(now);
+    inpState.injectKey(KSK::DOWN, KeyAction::Press);
+    inpState.injectKey(KSK::DOWN, KeyAction::Repeat);
+    inpState.endFrame();
+
+    // The OS repeat was ignored
+    assert_eq!(inpState.isStandardHeld(KSK::DOWN as u64), false);
+
+    inpState.beginFrame(now + Duration::from_millis(450));
+
+    assert_eq!(inpState.isStandardRepeated(KSK::DOWN as u64), false);
+
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(510));
+
+    assert_eq!(inpState.isStandardHeld(KSK::DOWN as u64), true);
+    assert_eq!(inpState.isStandardRepeated(KSK::DOWN as u64), true);
+
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(550));
+
+    assert_eq!(inpState.isStandardRepeated(KSK::DOWN as u64), false);
+
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(600));
+
+    assert_eq!(inpState.isStandardRepeated(KSK::DOWN as u64), true);
+}
+
+#[test]
+fn engineRepeatModifierTest()
+{
+    let now = Instant::now();
+    let mut inpState = InputState::new();
+    inpState.repeat = Some(RepeatSettings::fromRate(Duration::from_millis(500), 10));
+
+    inpState.beginFrame(now);
+    inpState.injectModifier(KMK::LShift, KeyAction::Press);
+    inpState.injectModifier(KMK::LShift, KeyAction::Repeat);
+    inpState.endFrame();
+
+    assert_eq!(inpState.isModifierHeld(KMK::LShift as u32), false);
+
+    inpState.beginFrame(now + Duration::from_millis(510));
+
+    assert_eq!(inpState.isModifierHeld(KMK::LShift as u32), true);
+
+    inpState.injectModifier(KMK::LShift, KeyAction::Release);
+
+    assert_eq!(inpState.isModifierHeld(KMK::LShift as u32), false);
+}
+
+#[test]
+fn actionRepeatTest()
+{
+    use crate::input::KSK;
+
+    let now = Instant::now();
+    let actions = ActionMap::defaults();
+    let mut repeats = ActionRepeats::new();
+    let mut inpState = InputState::new();
+
+    // Everything else repeats quickly, this player wants moving slower
+    inpState.repeat = Some(RepeatSettings::fromRate(Duration::from_millis(100), 20));
+    repeats.setRepeat("move_forward", Some(RepeatSettings::fromRate(Duration::from_millis(800), 2)));
+
+    inpState.beginFrame(now);
+    inpState.injectKey(KSK::W, KeyAction::Press);
+    inpState.injectKey(KSK::S, KeyAction::Press);
+    repeats.update(&actions, &inpState);
+    inpState.endFrame();
+
+    inpState.beginFrame(now + Duration::from_millis(200));
+    repeats.update(&actions, &inpState);
+
+    assert_eq!(repeats.isActionHeld("move_back", &actions, &inpState), true);
+    assert_eq!(repeats.isActionHeld("move_forward", &actions, &inpState), false);
+
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(850));
+    repeats.update(&actions, &inpState);
+
+    assert_eq!(repeats.isActionHeld("move_forward", &actions, &inpState), true);
+    assert_eq!(repeats.isActionRepeated("move_forward"), true);
+
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(900));
+    repeats.update(&actions, &inpState);
+
+    assert_eq!(repeats.isActionRepeated("move_forward"), false);
+
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(1350));
+    repeats.update(&actions, &inpState);
+
+    assert_eq!(repeats.isActionRepeated("move_forward"), true);
+}
+
+#[test]
+fn actionToggleTest()
+{
+    use crate::input::KSK;
+
+    let actions = ActionMap::defaults();
+    let mut toggles = ActionToggles::new();
+    let mut inpState = InputState::new();
+
+    toggles.setToggle("move_forward", true);
+
+    inpState.injectKey(KSK::W, KeyAction::Press);
+    toggles.update(&actions, &inpState);
+    inpState.injectKey(KSK::W, KeyAction::Release);
+    toggles.update(&actions, &inpState);
+
+    assert_eq!(toggles.isActionActive("move_forward", &actions, &inpState), true);
+    assert_eq!(toggles.isActionActive("move_back", &actions, &inpState), false);
+
+    inpState.injectKey(KSK::W, KeyAction::Press);
+    toggles.update(&actions, &inpState);
+
+    assert_eq!(toggles.isActionActive("move_forward", &actions, &inpState), false);
+}
+
+#[test]
+fn switchScannerTest()
+{
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use crate::input::KSK;
+
+    let now = Instant::now();
+    let selected = Rc::new(Cell::new(None));
+    let mut inpState = InputState::new();
+    let mut scanner = SwitchScanner::new(3, Duration::from_secs(1), InputBinding::Standard(KSK::SPACE));
+
+    let hook = selected.clone();
+    scanner.on_select = Some(Box::new(move |item| hook.set(Some(item))));
+
+    scanner.update(now, &inpState);
+    scanner.update(now + Duration::from_millis(1000), &inpState);
+    scanner.update(now + Duration::from_millis(2000), &inpState);
+
+    assert_eq!(scanner.current, 2);
+
+    scanner.update(now + Duration::from_millis(3000), &inpState);
+
+    assert_eq!(scanner.current, 0);
+
+    inpState.injectKey(KSK::SPACE, KeyAction::Press);
+    scanner.update(now + Duration::from_millis(3100), &inpState);
+
+    assert_eq!(selected.get(), Some(0));
+}
+
+/*************************************/
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use crate::input::{InputState, KSK, KMK, KMB};
+
+// A single physical input that an action can be bound to.
+// This is what lets gameplay code ask about "jump"
+// instead of asking about the space bar directly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputBinding
+{
+    // A key by where it sits. KSKs are named for what a US layout
+    // prints there, so Standard(KSK::W) is the key above S
+    // whatever the player's layout says on it.
+    Standard(KSK),
+    Modifier(KMK),
+    Mouse(KMB),
+    // A key with no KSK by its scancode, written "@94" in bindings
+    // files. Scancodes differ between platforms and keyboards, so
+    // these only suit bindings made on the machine they are used on.
+    Physical(i32),
+    // The key with this on it under the player's layout, written
+    // "label:A" in bindings files. For bindings named after what
+    // they do, like "I" for inventory, rather than where they sit.
+    Logical(char),
+}
+
+// Kestrel Action (KA)
+// The actions the engine binds by default.
+// Games are free to bind any other name they want,
+// these just save typing out the strings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KA
+{
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+}
+
+// Maps action names to every input bound to them.
+// An action is active when any one of its inputs is.
+pub struct ActionMap
+{
+    pub bindings: HashMap<String, Vec<InputBinding>>,
+}
+
+impl InputBinding
+{
+    pub fn isPressed(&self, inState: &InputState) -> bool
+    {
+        return match *self
+        {
+            InputBinding::Standard(key) =>  inState.isStandardPressed(key as u64),
+            InputBinding::Modifier(key) =>  inState.isModifierPressed(key as u32),
+            InputBinding::Mouse(button) =>  inState.isMouseButtonPressed(button as u16),
+            InputBinding::Physical(scancode) => inState.isPhysicalPressed(scancode),
+            InputBinding::Logical(label) => inState.layout.keyFor(label).map(|key| inState.isStandardPressed(key as u64)).unwrap_or(false),
+        };
+    }
+
+    pub fn isHeld(&self, inState: &InputState) -> bool
+    {
+        return match *self
+        {
+            InputBinding::Standard(key) =>  inState.isStandardHeld(key as u64),
+            InputBinding::Modifier(key) =>  inState.isModifierHeld(key as u32),
+            InputBinding::Mouse(button) =>  inState.isMouseButtonHeld(button as u16),
+            InputBinding::Physical(scancode) => inState.isPhysicalHeld(scancode),
+            InputBinding::Logical(label) => inState.layout.keyFor(label).map(|key| inState.isStandardHeld(key as u64)).unwrap_or(false),
+        };
+    }
+
+    pub fn name(&self) -> String
+    {
+        return match *self
+        {
+            InputBinding::Standard(key) =>  key.name().to_string(),
+            InputBinding::Modifier(key) =>  key.name().to_string(),
+            InputBinding::Mouse(button) =>  button.name().to_string(),
+            InputBinding::Physical(scancode) => format!("@{}", scancode),
+            InputBinding::Logical(label) => format!("label:{}", label),
+        };
+    }
+
+    // The key, modifier and mouse button names never overlap,
+    // so a bare name is enough to find the right one.
+    pub fn fromName(name: &str) -> Option<InputBinding>
+    {
+        if let Some(physical) = name.strip_prefix('@')
+        {
+            return physical.parse::<i32>().ok().map(InputBinding::Physical);
+        }
+        if let Some(label) = name.strip_prefix("label:")
+        {
+            let mut chars = label.chars();
+            return match (chars.next(), chars.next())
+            {
+                (Some(label), None) => Some(InputBinding::Logical(label.to_uppercase().next().unwrap_or(label))),
+                _ => None,
+            };
+        }
+        if let Some(key) = KSK::fromName(name)
+        {
+            return Some(InputBinding::Standard(key));
+        }
+        if let Some(key) = KMK::fromName(name)
+        {
+            return Some(InputBinding::Modifier(key));
+        }
+        if let Some(button) = KMB::fromName(name)
+        {
+            return Some(InputBinding::Mouse(button));
+        }
+        return None;
+    }
+}
+
+impl KA
+{
+    pub fn name(&self) -> &'static str
+    {
+        return match *self
+        {
+            KA::MoveForward =>  "move_forward",
+            KA::MoveBack =>     "move_back",
+            KA::MoveLeft =>     "move_left",
+            KA::MoveRight =>    "move_right",
+            KA::Jump =>         "jump",
+        };
+    }
+}
+
+// Lets every ActionMap function take either a KA or a plain &str
+impl AsRef<str> for KA
+{
+    fn as_ref(&self) -> &str
+    {
+        return self.name();
+    }
+}
+
+impl ActionMap
+{
+    pub fn new() -> ActionMap
+    {
+        ActionMap
+        {
+            bindings: HashMap::new()
+        }
+    }
+
+    // The bindings the engine ships with.
+    // These replace the old w_Active, a_Active, ... fields on InputState.
+    pub fn defaults() -> ActionMap
+    {
+        let mut ret = ActionMap::new();
+
+        ret.bind(KA::MoveForward, InputBinding::Standard(KSK::W));
+        ret.bind(KA::MoveBack, InputBinding::Standard(KSK::S));
+        ret.bind(KA::MoveLeft, InputBinding::Standard(KSK::A));
+        ret.bind(KA::MoveRight, InputBinding::Standard(KSK::D));
+        ret.bind(KA::Jump, InputBinding::Standard(KSK::SPACE));
+
+        return ret;
+    }
+
+    pub fn bind<A: AsRef<str>>(&mut self, action: A, input: InputBinding)
+    {
+        let inputs = self.bindings.entry(action.as_ref().to_string()).or_insert_with(Vec::new);
+        if !inputs.contains(&input)
+        {
+            inputs.push(input);
+        }
+    }
+
+    pub fn unbind<A: AsRef<str>>(&mut self, action: A, input: InputBinding)
+    {
+        if let Some(inputs) = self.bindings.get_mut(action.as_ref())
+        {
+            inputs.retain(|bound| *bound != input);
+        }
+    }
+
+    // Swaps one input for another, keeping its place in the list
+    pub fn rebind<A: AsRef<str>>(&mut self, action: A, from: InputBinding, to: InputBinding)
+    {
+        let inputs = self.bindings.entry(action.as_ref().to_string()).or_insert_with(Vec::new);
+        if inputs.contains(&to)
+        {
+            inputs.retain(|bound| *bound != from || from == to);
+            return;
+        }
+        match inputs.iter().position(|bound| *bound == from)
+        {
+            Some(index) => {inputs[index] = to;},
+            None => {inputs.push(to);},
+        }
+    }
+
+    pub fn clearAction<A: AsRef<str>>(&mut self, action: A)
+    {
+        self.bindings.remove(action.as_ref());
+    }
+
+    pub fn getBindings<A: AsRef<str>>(&self, action: A) -> &[InputBinding]
+    {
+        return match self.bindings.get(action.as_ref())
+        {
+            Some(inputs) => inputs,
+            None => &[],
+        };
+    }
+
+    // True when input is bound to any action at all
+    pub fn isBound(&self, input: InputBinding) -> bool
+    {
+        return self.bindings.values().any(|inputs| inputs.contains(&input));
+    }
+
+    pub fn isActionPressed<A: AsRef<str>>(&self, action: A, inState: &InputState) -> bool
+    {
+        return self.getBindings(action).iter().any(|input| input.isPressed(inState));
+    }
+
+    pub fn isActionHeld<A: AsRef<str>>(&self, action: A, inState: &InputState) -> bool
+    {
+        return self.getBindings(action).iter().any(|input| input.isHeld(inState));
+    }
+}
+
+/*************************************/
+// Action tests
+
+#[test]
+fn actionPressTest()
+{
+    let actions = ActionMap::defaults();
+    let mut inpState = InputState::new();
+
+    assert_eq!(actions.isActionPressed(KA::MoveForward, &inpState), false);
+
+    inpState.standard_keys_press[KSK::W as usize] = true;
+
+    assert_eq!(actions.isActionPressed(KA::MoveForward, &inpState), true);
+    assert_eq!(actions.isActionPressed("move_forward", &inpState), true);
+    assert_eq!(actions.isActionPressed(KA::MoveBack, &inpState), false);
+}
+
+#[test]
+fn actionMultipleBindingsTest()
+{
+    let mut actions = ActionMap::new();
+    let mut inpState = InputState::new();
+
+    actions.bind("fire", InputBinding::Mouse(KMB::M1));
+    actions.bind("fire", InputBinding::Standard(KSK::F));
+
+    inpState.standard_keys_press[KSK::F as usize] = true;
+
+    assert_eq!(actions.isActionPressed("fire", &inpState), true);
+
+    actions.unbind("fire", InputBinding::Standard(KSK::F));
+
+    assert_eq!(actions.isActionPressed("fire", &inpState), false);
+    assert_eq!(actions.getBindings("fire"), &[InputBinding::Mouse(KMB::M1)]);
+}
+
+#[test]
+fn actionUnknownTest()
+{
+    let actions = ActionMap::new();
+    let inpState = InputState::new();
+
+    assert_eq!(actions.isActionPressed("does_not_exist", &inpState), false);
+    assert_eq!(actions.getBindings("does_not_exist").len(), 0);
+}
+
+#[test]
+fn actionRebindTest()
+{
+    let mut actions = ActionMap::defaults();
+
+    actions.rebind(KA::MoveForward, InputBinding::Standard(KSK::W), InputBinding::Standard(KSK::Z));
+
+    assert_eq!(actions.getBindings(KA::MoveForward), &[InputBinding::Standard(KSK::Z)]);
+    assert_eq!(InputBinding::fromName("Z"), Some(InputBinding::Standard(KSK::Z)));
+    assert_eq!(InputBinding::fromName("M1"), Some(InputBinding::Mouse(KMB::M1)));
+}
+
+/*************************************/
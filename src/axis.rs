@@ -0,0 +1,375 @@
+use crate::input::{InputState, KSK};
+use crate::gamepad::{KGA, KGB};
+
+// Where a 1D axis gets its raw value from.
+// Keys and buttons read as -1, 0 or 1, gamepad axes read as is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisSource
+{
+    Keys(KSK, KSK),
+    GamepadButtons(usize, KGB, KGB),
+    Gamepad(usize, KGA),
+}
+
+// Where a 2D axis gets its raw value from.
+// Keys go up, down, left, right. Sticks are the slot then the x and y
+// axes, and have their y flipped so that pushing up is positive like
+// the keys are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis2DSource
+{
+    Keys(KSK, KSK, KSK, KSK),
+    Stick(usize, KGA, KGA),
+}
+
+// Axial checks each direction on its own, which snaps to straight
+// lines easily. Radial checks how far the stick is from the centre,
+// which keeps diagonals smooth. Both rescale what is left over so
+// the output still starts at 0 right at the edge of the dead zone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeadZone
+{
+    Axial(f32),
+    Radial(f32),
+}
+
+// How the distance past the dead zone maps to the output.
+// Steeper curves give finer control near the centre.
+// Power needs an exponent above 0, anything else is treated as Linear.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResponseCurve
+{
+    Linear,
+    Quadratic,
+    Cubic,
+    Power(f32),
+}
+
+// A virtual axis going from -1 to 1.
+// When several sources are bound the one pushed furthest wins,
+// so keys and a stick can drive the same axis.
+pub struct Axis1D
+{
+    pub sources: Vec<AxisSource>,
+    pub dead_zone: f32,
+    pub sensitivity: f32,
+    pub invert: bool,
+    pub curve: ResponseCurve,
+}
+
+// A virtual 2D axis whose length never goes over 1
+pub struct Axis2D
+{
+    pub sources: Vec<Axis2DSource>,
+    pub dead_zone: DeadZone,
+    pub sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub curve: ResponseCurve,
+}
+
+fn keyValue(inState: &InputState, negative: KSK, positive: KSK) -> f32
+{
+    let mut ret = 0.0;
+    if inState.isStandardPressed(negative as u64) { ret -= 1.0; }
+    if inState.isStandardPressed(positive as u64) { ret += 1.0; }
+    return ret;
+}
+
+fn buttonValue(inState: &InputState, slot: usize, negative: KGB, positive: KGB) -> f32
+{
+    let mut ret = 0.0;
+    if inState.isGamepadButtonPressed(slot, negative) { ret -= 1.0; }
+    if inState.isGamepadButtonPressed(slot, positive) { ret += 1.0; }
+    return ret;
+}
+
+// Rescales a 0 to 1 magnitude so the dead zone edge becomes 0
+fn pastDeadZone(magnitude: f32, deadZone: f32) -> f32
+{
+    if magnitude <= deadZone
+    {
+        return 0.0;
+    }
+    return ((magnitude - deadZone) / (1.0 - deadZone)).min(1.0);
+}
+
+impl AxisSource
+{
+    pub fn read(&self, inState: &InputState) -> f32
+    {
+        return match *self
+        {
+            AxisSource::Keys(negative, positive) =>                 keyValue(inState, negative, positive),
+            AxisSource::GamepadButtons(slot, negative, positive) => buttonValue(inState, slot, negative, positive),
+            AxisSource::Gamepad(slot, axis) =>                      inState.getGamepadAxis(slot, axis),
+        };
+    }
+}
+
+impl Axis2DSource
+{
+    pub fn read(&self, inState: &InputState) -> (f32, f32)
+    {
+        return match *self
+        {
+            Axis2DSource::Keys(up, down, left, right) =>
+            {
+                let x = keyValue(inState, left, right);
+                let y = keyValue(inState, down, up);
+                let length = (x * x + y * y).sqrt();
+                // Diagonals should not be faster than straight lines
+                if length > 1.0 { (x / length, y / length) } else { (x, y) }
+            },
+            Axis2DSource::Stick(slot, x, y) => (inState.getGamepadAxis(slot, x), -inState.getGamepadAxis(slot, y)),
+        };
+    }
+}
+
+impl ResponseCurve
+{
+    // Takes and returns a value from 0 to 1
+    pub fn apply(&self, value: f32) -> f32
+    {
+        return match *self
+        {
+            ResponseCurve::Linear =>            value,
+            ResponseCurve::Quadratic =>         value * value,
+            ResponseCurve::Cubic =>             value * value * value,
+            // 0 to the power of 0 is 1, which would put a resting stick at full tilt
+            ResponseCurve::Power(exponent) if exponent > 0.0 => value.powf(exponent),
+            ResponseCurve::Power(_) =>          value,
+        };
+    }
+}
+
+impl Axis1D
+{
+    pub fn new() -> Axis1D
+    {
+        Axis1D
+        {
+            sources: Vec::new(),
+            dead_zone: 0.0,
+            sensitivity: 1.0,
+            invert: false,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    pub fn addSource(&mut self, source: AxisSource)
+    {
+        self.sources.push(source);
+    }
+
+    pub fn value(&self, inState: &InputState) -> f32
+    {
+        let mut raw: f32 = 0.0;
+        for source in &self.sources
+        {
+            let value = source.read(inState);
+            if value.abs() > raw.abs()
+            {
+                raw = value;
+            }
+        }
+
+        let magnitude = self.curve.apply(pastDeadZone(raw.abs().min(1.0), self.dead_zone));
+        let ret = (magnitude * self.sensitivity).min(1.0) * raw.signum();
+
+        return if self.invert { -ret } else { ret };
+    }
+}
+
+impl Axis2D
+{
+    pub fn new() -> Axis2D
+    {
+        Axis2D
+        {
+            sources: Vec::new(),
+            dead_zone: DeadZone::Radial(0.0),
+            sensitivity: 1.0,
+            invert_x: false,
+            invert_y: false,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    // W/A/S/D, what the default movement actions use
+    pub fn wasd() -> Axis2D
+    {
+        let mut ret = Axis2D::new();
+        ret.addSource(Axis2DSource::Keys(KSK::W, KSK::S, KSK::A, KSK::D));
+        return ret;
+    }
+
+    pub fn arrows() -> Axis2D
+    {
+        let mut ret = Axis2D::new();
+        ret.addSource(Axis2DSource::Keys(KSK::UP, KSK::DOWN, KSK::LEFT, KSK::RIGHT));
+        return ret;
+    }
+
+    pub fn leftStick(slot: usize) -> Axis2D
+    {
+        let mut ret = Axis2D::new();
+        ret.addSource(Axis2DSource::Stick(slot, KGA::LeftX, KGA::LeftY));
+        ret.dead_zone = DeadZone::Radial(0.15);
+        return ret;
+    }
+
+    pub fn addSource(&mut self, source: Axis2DSource)
+    {
+        self.sources.push(source);
+    }
+
+    pub fn value(&self, inState: &InputState) -> (f32, f32)
+    {
+        let mut raw: (f32, f32) = (0.0, 0.0);
+        for source in &self.sources
+        {
+            let value = source.read(inState);
+            if value.0 * value.0 + value.1 * value.1 > raw.0 * raw.0 + raw.1 * raw.1
+            {
+                raw = value;
+            }
+        }
+
+        let (x, y) = match self.dead_zone
+        {
+            DeadZone::Axial(size) =>
+                (pastDeadZone(raw.0.abs(), size) * raw.0.signum(), pastDeadZone(raw.1.abs(), size) * raw.1.signum()),
+            DeadZone::Radial(size) =>
+            {
+                let length = (raw.0 * raw.0 + raw.1 * raw.1).sqrt();
+                if length == 0.0
+                {
+                    (0.0, 0.0)
+                }
+                else
+                {
+                    let scale = pastDeadZone(length.min(1.0), size) / length;
+                    (raw.0 * scale, raw.1 * scale)
+                }
+            },
+        };
+
+        // The curve and sensitivity work on the length so the
+        // direction the stick is pointing never changes
+        let length = (x * x + y * y).sqrt();
+        if length == 0.0
+        {
+            return (0.0, 0.0);
+        }
+        let scale = (self.curve.apply(length.min(1.0)) * self.sensitivity).min(1.0) / length;
+
+        let mut ret = (x * scale, y * scale);
+        if self.invert_x { ret.0 = -ret.0; }
+        if self.invert_y { ret.1 = -ret.1; }
+
+        return ret;
+    }
+}
+
+/*************************************/
+// Axis tests
+
+#[test]
+fn keyAxisTest()
+{
+    let mut inpState = InputState::new();
+    let mut axis = Axis1D::new();
+    axis.addSource(AxisSource::Keys(KSK::A, KSK::D));
+
+    assert_eq!(axis.value(&inpState), 0.0);
+
+    inpState.standard_keys_press[KSK::A as usize] = true;
+
+    assert_eq!(axis.value(&inpState), -1.0);
+
+    axis.invert = true;
+
+    assert_eq!(axis.value(&inpState), 1.0);
+}
+
+#[test]
+fn wasdDiagonalTest()
+{
+    let mut inpState = InputState::new();
+    let axis = Axis2D::wasd();
+
+    inpState.standard_keys_press[KSK::W as usize] = true;
+    inpState.standard_keys_press[KSK::D as usize] = true;
+
+    let (x, y) = axis.value(&inpState);
+
+    assert!((x - 0.70710677).abs() < 0.0001);
+    assert!((y - 0.70710677).abs() < 0.0001);
+}
+
+#[test]
+fn stickDeadZoneTest()
+{
+    use crate::event::InputEvent;
+
+    let mut inpState = InputState::new();
+    let mut axis = Axis2D::leftStick(0);
+
+    inpState.handleInputEvent(InputEvent::ControllerConnected(0));
+    inpState.handleInputEvent(InputEvent::ControllerAxis(0, KGA::LeftX, 0.1));
+
+    assert_eq!(axis.value(&inpState), (0.0, 0.0));
+
+    // Stick y is down positive, the axis is up positive
+    inpState.handleInputEvent(InputEvent::ControllerAxis(0, KGA::LeftX, 0.0));
+    inpState.handleInputEvent(InputEvent::ControllerAxis(0, KGA::LeftY, -1.0));
+
+    assert_eq!(axis.value(&inpState), (0.0, 1.0));
+
+    // Half way past the dead zone, squared
+    axis.curve = ResponseCurve::Quadratic;
+    inpState.handleInputEvent(InputEvent::ControllerAxis(0, KGA::LeftY, -0.575));
+
+    assert!((axis.value(&inpState).1 - 0.25).abs() < 0.0001);
+}
+
+#[test]
+fn powerCurveTest()
+{
+    let mut inpState = InputState::new();
+    let mut axis = Axis1D::new();
+    axis.addSource(AxisSource::Gamepad(0, KGA::LeftX));
+
+    for exponent in [0.0, -2.0, f32::NAN]
+    {
+        axis.curve = ResponseCurve::Power(exponent);
+
+        assert_eq!(axis.value(&inpState), 0.0);
+    }
+
+    axis.curve = ResponseCurve::Power(0.5);
+    inpState.controllers[0].axes[KGA::LeftX as usize] = -0.25;
+
+    assert_eq!(axis.value(&inpState), -0.5);
+}
+
+#[test]
+fn axialDeadZoneTest()
+{
+    let mut inpState = InputState::new();
+    let mut axis = Axis2D::new();
+    axis.addSource(Axis2DSource::Stick(0, KGA::RightX, KGA::RightY));
+    axis.dead_zone = DeadZone::Axial(0.2);
+
+    inpState.controllers[0].axes[KGA::RightX as usize] = 0.6;
+    inpState.controllers[0].axes[KGA::RightY as usize] = 0.1;
+
+    // The small y is dropped, so the stick snaps to straight right
+    let (x, y) = axis.value(&inpState);
+
+    assert!((x - 0.5).abs() < 0.0001);
+    assert_eq!(y, 0.0);
+}
+
+/*************************************/
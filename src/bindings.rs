@@ -0,0 +1,229 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActionMap, InputBinding};
+
+// Bindings files look like this:
+//
+// [actions]
+// move_forward = ["W"]
+// fire = ["M1", "F"]
+//
+// Each input is written with the same name as its
+// KSK, KMK or KMB variant. KSKs are positions on a US
+// keyboard, "label:A" is the key with A on it instead
+// and "@94" is a raw scancode.
+#[derive(Serialize, Deserialize)]
+struct BindingsFile
+{
+    actions: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum BindingsError
+{
+    Io(io::Error),
+    Parse(String),
+    UnknownInput { action: String, input: String },
+    Conflict { input: String, first: String, second: String },
+}
+
+impl fmt::Display for BindingsError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        return match self
+        {
+            BindingsError::Io(err) =>       write!(f, "could not access bindings file: {}", err),
+            BindingsError::Parse(err) =>    write!(f, "could not parse bindings file: {}", err),
+            BindingsError::UnknownInput { action, input } =>
+                write!(f, "action \"{}\" is bound to unknown input \"{}\"", action, input),
+            BindingsError::Conflict { input, first, second } =>
+                write!(f, "input \"{}\" is bound to both \"{}\" and \"{}\"", input, first, second),
+        };
+    }
+}
+
+impl From<io::Error> for BindingsError
+{
+    fn from(err: io::Error) -> BindingsError
+    {
+        return BindingsError::Io(err);
+    }
+}
+
+// Makes sure no input drives more than one action.
+// Actions are checked in name order so the error is the same every run.
+pub fn validateBindings(actions: &ActionMap) -> Result<(), BindingsError>
+{
+    let mut names: Vec<&String> = actions.bindings.keys().collect();
+    names.sort();
+
+    let mut owners: HashMap<String, &String> = HashMap::new();
+    for name in names
+    {
+        for input in &actions.bindings[name]
+        {
+            if let Some(first) = owners.insert(input.name(), name)
+            {
+                return Err(BindingsError::Conflict
+                {
+                    input: input.name(),
+                    first: first.clone(),
+                    second: name.clone(),
+                });
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+pub fn parseBindings(text: &str) -> Result<ActionMap, BindingsError>
+{
+    let file: BindingsFile = toml::from_str(text).map_err(|err| BindingsError::Parse(err.to_string()))?;
+
+    let mut ret = ActionMap::new();
+    for (action, inputs) in &file.actions
+    {
+        // Keep actions with nothing bound so they survive a save
+        ret.bindings.entry(action.clone()).or_insert_with(Vec::new);
+
+        for input in inputs
+        {
+            match InputBinding::fromName(input)
+            {
+                Some(binding) => {ret.bind(action, binding);},
+                None =>
+                {
+                    return Err(BindingsError::UnknownInput
+                    {
+                        action: action.clone(),
+                        input: input.clone(),
+                    });
+                },
+            }
+        }
+    }
+
+    validateBindings(&ret)?;
+
+    return Ok(ret);
+}
+
+pub fn bindingsToString(actions: &ActionMap) -> String
+{
+    let mut file = BindingsFile
+    {
+        actions: BTreeMap::new()
+    };
+
+    for (action, inputs) in &actions.bindings
+    {
+        file.actions.insert(action.clone(), inputs.iter().map(|input| input.name()).collect());
+    }
+
+    return toml::to_string(&file).expect("Failed to serialize bindings.");
+}
+
+pub fn loadBindings(path: &str) -> Result<ActionMap, BindingsError>
+{
+    let text = fs::read_to_string(path)?;
+    return parseBindings(&text);
+}
+
+// Loads the bindings at path, falling back to the defaults if
+// the file is missing or broken. A broken file is left alone
+// so the player can fix it by hand.
+pub fn loadBindingsOrDefault(path: &str) -> ActionMap
+{
+    return match loadBindings(path)
+    {
+        Ok(actions) => actions,
+        Err(BindingsError::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => ActionMap::defaults(),
+        Err(err) =>
+        {
+            println!("Using default bindings, {}", err);
+            ActionMap::defaults()
+        },
+    };
+}
+
+// Validates before writing so a bad rebind never reaches disk
+pub fn saveBindings(actions: &ActionMap, path: &str) -> Result<(), BindingsError>
+{
+    validateBindings(actions)?;
+    fs::write(path, bindingsToString(actions))?;
+    return Ok(());
+}
+
+/*************************************/
+// Bindings tests
+
+#[test]
+fn bindingsRoundTripTest()
+{
+    let mut actions = ActionMap::defaults();
+    actions.bind("fire", InputBinding::Mouse(crate::input::KMB::M1));
+
+    let loaded = parseBindings(&bindingsToString(&actions)).unwrap();
+
+    assert_eq!(loaded.bindings, actions.bindings);
+}
+
+#[test]
+fn bindingsUnknownInputTest()
+{
+    let text = "[actions]\nmove_forward = [\"W\", \"NOT_A_KEY\"]\n";
+
+    match parseBindings(text)
+    {
+        Err(BindingsError::UnknownInput { action, input }) =>
+        {
+            assert_eq!(action, "move_forward");
+            assert_eq!(input, "NOT_A_KEY");
+        },
+        _ => panic!("expected an unknown input error"),
+    }
+}
+
+#[test]
+fn bindingsConflictTest()
+{
+    let text = "[actions]\nmove_forward = [\"W\"]\njump = [\"W\"]\n";
+
+    match parseBindings(text)
+    {
+        Err(BindingsError::Conflict { input, first, second }) =>
+        {
+            assert_eq!(input, "W");
+            assert_eq!(first, "jump");
+            assert_eq!(second, "move_forward");
+        },
+        _ => panic!("expected a conflict error"),
+    }
+}
+
+#[test]
+fn bindingsParseErrorTest()
+{
+    match parseBindings("this is not toml [")
+    {
+        Err(BindingsError::Parse(_)) => {},
+        _ => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn bindingsMissingFileTest()
+{
+    let actions = loadBindingsOrDefault("this/file/does/not/exist.toml");
+
+    assert_eq!(actions.bindings, ActionMap::defaults().bindings);
+}
+
+/*************************************/
@@ -0,0 +1,390 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::input::{KSK, KMB};
+use crate::gamepad::KGB;
+
+const HISTORY_CAPACITY: usize = 128;
+const HISTORY_MAX_AGE: Duration = Duration::from_secs(2);
+
+// Anything that can be part of a combo.
+// Diagonals on a keyboard are just two keys, so they are chords.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ComboInput
+{
+    Key(KSK),
+    Mouse(KMB),
+    Pad(KGB),
+}
+
+// One press or release the history remembers. seq only ever
+// goes up, so entries within the same frame still have an order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HistoryEntry
+{
+    pub input: ComboInput,
+    pub pressed: bool,
+    pub time: Instant,
+    pub seq: u64,
+}
+
+// The last few seconds of presses and releases, oldest first.
+// InputState fills this in as events come through.
+#[derive(Clone)]
+pub struct InputHistory
+{
+    pub entries: VecDeque<HistoryEntry>,
+    next_seq: u64,
+}
+
+// A named sequence of steps. A step with one input is a plain press,
+// a step with several is a chord, where every input has to be down at
+// once. The ones pressed for the chord have to go down within
+// chord_window of each other in any order, the rest can already be
+// held from before, so Down then Down+Forward works on a keyboard.
+// Each step has to start within step_window of the one before it
+// finishing. Presses of inputs the combo does not use are skipped.
+// When two combos finish on the same press the higher priority wins,
+// then the longer one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Combo
+{
+    pub name: String,
+    pub steps: Vec<Vec<ComboInput>>,
+    pub step_window: Duration,
+    pub chord_window: Duration,
+    pub priority: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComboEvent
+{
+    pub name: String,
+    pub time: Instant,
+}
+
+// Watches an InputHistory for combos. Presses used up by a combo
+// can not start or finish another one.
+pub struct ComboMatcher
+{
+    pub combos: Vec<Combo>,
+    // Everything up to and including this seq has been looked at
+    checked: Option<u64>,
+    // Everything up to and including this seq was used by a combo
+    consumed: Option<u64>,
+}
+
+impl InputHistory
+{
+    pub fn new() -> InputHistory
+    {
+        InputHistory
+        {
+            entries: VecDeque::with_capacity(HISTORY_CAPACITY),
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, input: ComboInput, time: Instant)
+    {
+        self.add(input, true, time);
+    }
+
+    pub fn pushRelease(&mut self, input: ComboInput, time: Instant)
+    {
+        self.add(input, false, time);
+    }
+
+    fn add(&mut self, input: ComboInput, pressed: bool, time: Instant)
+    {
+        while self.entries.len() >= HISTORY_CAPACITY
+        {
+            self.entries.pop_front();
+        }
+        while self.entries.front().map(|entry| time.duration_since(entry.time) > HISTORY_MAX_AGE).unwrap_or(false)
+        {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(HistoryEntry { input, pressed, time, seq: self.next_seq });
+        self.next_seq += 1;
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.entries.clear();
+    }
+}
+
+impl Combo
+{
+    pub fn new(name: &str, steps: Vec<Vec<ComboInput>>) -> Combo
+    {
+        Combo
+        {
+            name: name.to_string(),
+            steps,
+            step_window: Duration::from_millis(250),
+            chord_window: Duration::from_millis(50),
+            priority: 0,
+        }
+    }
+
+    fn uses(&self, input: ComboInput) -> bool
+    {
+        return self.steps.iter().any(|step| step.contains(&input));
+    }
+
+    // Checks whether this combo finishes with the press at entries[end - 1].
+    // Returns the index of the first entry it used.
+    fn matchesAt(&self, entries: &[HistoryEntry], end: usize) -> Option<usize>
+    {
+        let newest = entries.get(end.checked_sub(1)?)?;
+        if !newest.pressed || !self.steps.last()?.contains(&newest.input)
+        {
+            return None;
+        }
+
+        let mut end = end;
+        let mut nextStart: Option<Instant> = None;
+
+        for (stepIndex, step) in self.steps.iter().enumerate().rev()
+        {
+            // A step finishes on the newest press the combo uses,
+            // anything else in between is stepped over
+            let finish = (0..end).rev().find(|index| entries[*index].pressed && self.uses(entries[*index].input))?;
+            if !step.contains(&entries[finish].input)
+            {
+                return None;
+            }
+
+            // The rest of a chord has to be down by then. Either it went
+            // down for this chord, or it has been held since an earlier step.
+            let mut start = finish;
+            for input in step.iter().filter(|input| **input != entries[finish].input)
+            {
+                let held = heldSince(entries, finish, *input)?;
+                if entries[finish].time.duration_since(entries[held].time) <= self.chord_window
+                {
+                    start = start.min(held);
+                }
+                else if !self.steps[..stepIndex].iter().any(|earlier| earlier.contains(input))
+                {
+                    return None;
+                }
+            }
+
+            if let Some(next) = nextStart
+            {
+                if next.duration_since(entries[finish].time) > self.step_window
+                {
+                    return None;
+                }
+            }
+
+            nextStart = Some(entries[start].time);
+            end = start;
+        }
+
+        return Some(end);
+    }
+}
+
+// Where input went down, if it went down before entries[at]
+// and has not come back up since
+fn heldSince(entries: &[HistoryEntry], at: usize, input: ComboInput) -> Option<usize>
+{
+    let index = entries[..at].iter().rposition(|entry| entry.input == input)?;
+    return if entries[index].pressed { Some(index) } else { None };
+}
+
+impl ComboMatcher
+{
+    pub fn new() -> ComboMatcher
+    {
+        ComboMatcher
+        {
+            combos: Vec::new(),
+            checked: None,
+            consumed: None,
+        }
+    }
+
+    pub fn addCombo(&mut self, combo: Combo)
+    {
+        self.combos.push(combo);
+    }
+
+    // Looks at every press added since the last check and
+    // returns the combos they finished, in order.
+    pub fn check(&mut self, history: &InputHistory) -> Vec<ComboEvent>
+    {
+        let mut ret = Vec::new();
+
+        let firstFree = match self.consumed
+        {
+            Some(seq) => history.entries.iter().position(|entry| entry.seq > seq).unwrap_or(history.entries.len()),
+            None => 0,
+        };
+        let mut start = firstFree;
+        let entries: Vec<HistoryEntry> = history.entries.iter().cloned().collect();
+
+        for end in 0..entries.len()
+        {
+            if self.checked.map(|seq| entries[end].seq <= seq).unwrap_or(false)
+            {
+                continue;
+            }
+            self.checked = Some(entries[end].seq);
+
+            let free = &entries[start..end + 1];
+            let mut best: Option<&Combo> = None;
+            for combo in &self.combos
+            {
+                if combo.matchesAt(free, free.len()).is_none()
+                {
+                    continue;
+                }
+                let better = match best
+                {
+                    Some(current) => (combo.priority, combo.steps.len()) > (current.priority, current.steps.len()),
+                    None => true,
+                };
+                if better
+                {
+                    best = Some(combo);
+                }
+            }
+
+            if let Some(combo) = best
+            {
+                ret.push(ComboEvent { name: combo.name.clone(), time: entries[end].time });
+                self.consumed = Some(entries[end].seq);
+                start = end + 1;
+            }
+        }
+
+        return ret;
+    }
+}
+
+/*************************************/
+// Combo tests
+
+#[test]
+fn comboSequenceTest()
+{
+    let now = Instant::now();
+    let mut history = InputHistory::new();
+    let mut matcher = ComboMatcher::new();
+
+    matcher.addCombo(Combo::new("fireball", vec![
+        vec![ComboInput::Key(KSK::S)],
+        vec![ComboInput::Key(KSK::S), ComboInput::Key(KSK::D)],
+        vec![ComboInput::Key(KSK::J)],
+    ]));
+
+    // Down, then forward while still holding down, then punch.
+    // The stray E press is not part of the combo, so it does not break it.
+    history.push(ComboInput::Key(KSK::S), now);
+    history.push(ComboInput::Key(KSK::D), now + Duration::from_millis(100));
+    history.pushRelease(ComboInput::Key(KSK::S), now + Duration::from_millis(130));
+    history.push(ComboInput::Key(KSK::E), now + Duration::from_millis(150));
+
+    assert_eq!(matcher.check(&history).len(), 0);
+
+    history.push(ComboInput::Key(KSK::J), now + Duration::from_millis(200));
+
+    assert_eq!(matcher.check(&history), vec![ComboEvent { name: "fireball".to_string(), time: now + Duration::from_millis(200) }]);
+
+    // The presses were used up, so it does not fire twice
+    assert_eq!(matcher.check(&history).len(), 0);
+}
+
+#[test]
+fn comboChordReleasedTest()
+{
+    let now = Instant::now();
+    let mut history = InputHistory::new();
+    let mut matcher = ComboMatcher::new();
+
+    matcher.addCombo(Combo::new("fireball", vec![
+        vec![ComboInput::Key(KSK::S)],
+        vec![ComboInput::Key(KSK::S), ComboInput::Key(KSK::D)],
+        vec![ComboInput::Key(KSK::J)],
+    ]));
+
+    // Down was let go before forward, so there was never a diagonal
+    history.push(ComboInput::Key(KSK::S), now);
+    history.pushRelease(ComboInput::Key(KSK::S), now + Duration::from_millis(60));
+    history.push(ComboInput::Key(KSK::D), now + Duration::from_millis(100));
+    history.push(ComboInput::Key(KSK::J), now + Duration::from_millis(200));
+
+    assert_eq!(matcher.check(&history).len(), 0);
+}
+
+#[test]
+fn comboWindowTest()
+{
+    let now = Instant::now();
+    let mut history = InputHistory::new();
+    let mut matcher = ComboMatcher::new();
+
+    matcher.addCombo(Combo::new("dash", vec![vec![ComboInput::Key(KSK::D)], vec![ComboInput::Key(KSK::D)]]));
+
+    history.push(ComboInput::Key(KSK::D), now);
+    history.push(ComboInput::Key(KSK::D), now + Duration::from_millis(400));
+
+    assert_eq!(matcher.check(&history).len(), 0);
+
+    history.push(ComboInput::Key(KSK::D), now + Duration::from_millis(500));
+
+    assert_eq!(matcher.check(&history).len(), 1);
+}
+
+#[test]
+fn comboPriorityTest()
+{
+    let now = Instant::now();
+    let mut history = InputHistory::new();
+    let mut matcher = ComboMatcher::new();
+
+    let mut punch = Combo::new("punch", vec![vec![ComboInput::Pad(KGB::X)]]);
+    punch.priority = -1;
+    matcher.addCombo(punch);
+    matcher.addCombo(Combo::new("uppercut", vec![vec![ComboInput::Pad(KGB::DpadDown)], vec![ComboInput::Pad(KGB::X)]]));
+    matcher.addCombo(Combo::new("throw", vec![vec![ComboInput::Pad(KGB::X), ComboInput::Pad(KGB::A)]]));
+
+    history.push(ComboInput::Pad(KGB::DpadDown), now);
+    history.push(ComboInput::Pad(KGB::X), now + Duration::from_millis(100));
+
+    assert_eq!(matcher.check(&history)[0].name, "uppercut");
+
+    history.push(ComboInput::Pad(KGB::A), now + Duration::from_millis(500));
+    history.push(ComboInput::Pad(KGB::X), now + Duration::from_millis(520));
+
+    assert_eq!(matcher.check(&history)[0].name, "throw");
+}
+
+#[test]
+fn comboFromInputStateTest()
+{
+    use crate::input::InputState;
+    use crate::event::{InputEvent, KeyAction, KeyMods};
+
+    let mut inpState = InputState::new();
+    let mut matcher = ComboMatcher::new();
+
+    matcher.addCombo(Combo::new("save", vec![vec![ComboInput::Key(KSK::S), ComboInput::Mouse(KMB::M1)]]));
+
+    inpState.handleInputEvent(InputEvent::MouseButton(KMB::M1, KeyAction::Press, KeyMods::NONE));
+    inpState.handleInputEvent(InputEvent::Key(KSK::S, KeyAction::Repeat, KeyMods::NONE));
+
+    assert_eq!(matcher.check(&inpState.history).len(), 0);
+
+    inpState.handleInputEvent(InputEvent::Key(KSK::S, KeyAction::Press, KeyMods::NONE));
+
+    assert_eq!(matcher.check(&inpState.history).len(), 1);
+}
+
+/*************************************/
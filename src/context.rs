@@ -0,0 +1,350 @@
+use std::time::Instant;
+
+use crate::action::{ActionMap, InputBinding};
+use crate::event::{InputEvent, KeyAction};
+use crate::input::InputState;
+use crate::layout::KeyLayout;
+
+// How much input a context keeps from the contexts under it.
+// Pass lets everything through, good for a HUD that only adds a
+// few keys. Bound keeps only the inputs its actions use. All keeps
+// everything, which is what menus and the debug console want.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConsumeMode
+{
+    Pass,
+    Bound,
+    All,
+}
+
+// One layer of input, with its own bindings and its own view of
+// the input state. It only sees what the layers above let through.
+pub struct InputContext
+{
+    pub name: String,
+    pub actions: ActionMap,
+    pub consume: ConsumeMode,
+    pub state: InputState,
+}
+
+// The active contexts, bottom first. Events go to the top
+// context first and work their way down until one consumes them.
+pub struct ContextStack
+{
+    contexts: Vec<InputContext>,
+    layout: KeyLayout,
+}
+
+// Releases, focus changes, cursor leaving and controllers coming and
+// going always reach every layer. Otherwise a key held before a menu
+// opened would stay stuck down in gameplay once the menu closed.
+// Stick positions and the cursor position do too, so nothing reads
+// as still tilted or somewhere else once the layers above are gone.
+fn alwaysDelivered(event: &InputEvent) -> bool
+{
+    return match *event
+    {
+        InputEvent::Key(_, KeyAction::Release, _) =>           true,
+        InputEvent::Modifier(_, KeyAction::Release) =>         true,
+        InputEvent::MouseButton(_, KeyAction::Release, _) =>   true,
+        InputEvent::ControllerButton(_, _, KeyAction::Release) => true,
+        InputEvent::CursorEntered(_) =>                         true,
+        InputEvent::Focus(_) =>                                 true,
+        InputEvent::Physical(_, KeyAction::Release) =>          true,
+        InputEvent::ControllerConnected(_) =>                   true,
+        InputEvent::ControllerDisconnected(_) =>                true,
+        InputEvent::ControllerAxis(_, _, _) =>                  true,
+        InputEvent::CursorMoved(_, _) =>                        true,
+        _ => false,
+    };
+}
+
+// The binding an event would trigger, if it is one that can be bound
+fn eventBinding(event: &InputEvent) -> Option<InputBinding>
+{
+    return match *event
+    {
+        InputEvent::Key(key, _, _) =>           Some(InputBinding::Standard(key)),
+        InputEvent::Modifier(key, _) =>         Some(InputBinding::Modifier(key)),
+        InputEvent::MouseButton(button, _, _) => Some(InputBinding::Mouse(button)),
+        InputEvent::Physical(scancode, _) =>    Some(InputBinding::Physical(scancode)),
+        _ => None,
+    };
+}
+
+// The same for bindings by label, which depend on the layout
+fn logicalBinding(event: &InputEvent, inState: &InputState) -> Option<InputBinding>
+{
+    return match *event
+    {
+        InputEvent::Key(key, _, _) =>   inState.layout.labelOf(key).map(InputBinding::Logical),
+        _ => None,
+    };
+}
+
+impl InputContext
+{
+    pub fn new(name: &str, actions: ActionMap, consume: ConsumeMode) -> InputContext
+    {
+        InputContext
+        {
+            name: name.to_string(),
+            actions,
+            consume,
+            state: InputState::new(),
+        }
+    }
+
+    pub fn consumes(&self, event: &InputEvent) -> bool
+    {
+        return match self.consume
+        {
+            ConsumeMode::Pass =>    false,
+            ConsumeMode::Bound =>   eventBinding(event).map(|input| self.actions.isBound(input)).unwrap_or(false)
+                || logicalBinding(event, &self.state).map(|input| self.actions.isBound(input)).unwrap_or(false),
+            ConsumeMode::All =>     true,
+        };
+    }
+
+    pub fn isActionPressed<A: AsRef<str>>(&self, action: A) -> bool
+    {
+        return self.actions.isActionPressed(action, &self.state);
+    }
+
+    pub fn isActionHeld<A: AsRef<str>>(&self, action: A) -> bool
+    {
+        return self.actions.isActionHeld(action, &self.state);
+    }
+}
+
+impl ContextStack
+{
+    pub fn new() -> ContextStack
+    {
+        ContextStack
+        {
+            contexts: Vec::new(),
+            layout: KeyLayout::us(),
+        }
+    }
+
+    // The new context starts with nothing pressed, so keys
+    // already down underneath do not leak into it.
+    pub fn push(&mut self, context: InputContext)
+    {
+        let mut context = context;
+        context.state.setLayout(self.layout.clone());
+        self.contexts.push(context);
+    }
+
+    pub fn pop(&mut self) -> Option<InputContext>
+    {
+        return self.contexts.pop();
+    }
+
+    // Takes a context out wherever it is in the stack
+    pub fn remove(&mut self, name: &str) -> Option<InputContext>
+    {
+        let index = self.contexts.iter().position(|context| context.name == name)?;
+        return Some(self.contexts.remove(index));
+    }
+
+    pub fn top(&self) -> Option<&InputContext>
+    {
+        return self.contexts.last();
+    }
+
+    // Usually gameplay, the one everything else gets pushed over
+    pub fn bottom(&self) -> Option<&InputContext>
+    {
+        return self.contexts.first();
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InputContext>
+    {
+        return self.contexts.iter().find(|context| context.name == name);
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut InputContext>
+    {
+        return self.contexts.iter_mut().find(|context| context.name == name);
+    }
+
+    pub fn isActive(&self, name: &str) -> bool
+    {
+        return self.get(name).is_some();
+    }
+
+    pub fn len(&self) -> usize
+    {
+        return self.contexts.len();
+    }
+
+    // Asks the named context about an action, false if it is not pushed
+    pub fn isActionPressed<A: AsRef<str>>(&self, name: &str, action: A) -> bool
+    {
+        return self.get(name).map(|context| context.isActionPressed(action)).unwrap_or(false);
+    }
+
+    pub fn beginFrame(&mut self, now: Instant)
+    {
+        for context in self.contexts.iter_mut()
+        {
+            context.state.beginFrame(now);
+        }
+    }
+
+    pub fn endFrame(&mut self)
+    {
+        for context in self.contexts.iter_mut()
+        {
+            context.state.endFrame();
+        }
+    }
+
+    // Every context, and any pushed later, reads labels the same way
+    pub fn setLayout(&mut self, layout: KeyLayout)
+    {
+        for context in self.contexts.iter_mut()
+        {
+            context.state.setLayout(layout.clone());
+        }
+        self.layout = layout;
+    }
+
+    pub fn setCursorCaptured(&mut self, captured: bool)
+    {
+        for context in self.contexts.iter_mut()
+        {
+            context.state.setCursorCaptured(captured);
+        }
+    }
+
+    // Returns true if the bottom context saw the event as input,
+    // which is what the tick threads get to see
+    pub fn handleInputEvent(&mut self, event: InputEvent) -> bool
+    {
+        let always = alwaysDelivered(&event);
+        let mut covered = false;
+
+        for context in self.contexts.iter_mut().rev()
+        {
+            match event
+            {
+                // Layers under one that wanted the motion keep up with
+                // where the cursor is without seeing it move
+                InputEvent::CursorMoved(x, y) if covered => context.state.placeCursor(x, y),
+                _ => context.state.handleInputEvent(event),
+            }
+            if context.consumes(&event)
+            {
+                if !always
+                {
+                    return false;
+                }
+                covered = true;
+            }
+        }
+
+        return match event
+        {
+            InputEvent::CursorMoved(_, _) => !covered,
+            _ => true,
+        };
+    }
+}
+
+/*************************************/
+// Context tests
+
+#[test]
+fn contextConsumeAllTest()
+{
+    use crate::input::KSK;
+    use crate::event::KeyMods;
+
+    let mut contexts = ContextStack::new();
+    contexts.push(InputContext::new("gameplay", ActionMap::defaults(), ConsumeMode::Pass));
+    contexts.push(InputContext::new("menu", ActionMap::new(), ConsumeMode::All));
+
+    assert_eq!(contexts.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE)), false);
+
+    assert_eq!(contexts.isActionPressed("gameplay", "move_forward"), false);
+    assert_eq!(contexts.top().unwrap().state.isStandardPressed(KSK::W as u64), true);
+
+    // Reaches gameplay, but only as a position, not as motion
+    assert_eq!(contexts.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Release, KeyMods::NONE)), true);
+    assert_eq!(contexts.handleInputEvent(InputEvent::CursorMoved(5.0, 5.0)), false);
+
+    contexts.pop();
+
+    assert_eq!(contexts.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE)), true);
+    assert_eq!(contexts.isActionPressed("gameplay", "move_forward"), true);
+    assert_eq!(contexts.bottom().unwrap().name, "gameplay");
+}
+
+#[test]
+fn contextConsumeBoundTest()
+{
+    use crate::input::KSK;
+    use crate::event::KeyMods;
+
+    let mut console = ActionMap::new();
+    console.bind("close_console", InputBinding::Standard(KSK::GRAVE_ACCENT));
+
+    let mut contexts = ContextStack::new();
+    contexts.push(InputContext::new("gameplay", ActionMap::defaults(), ConsumeMode::Pass));
+    contexts.push(InputContext::new("console", console, ConsumeMode::Bound));
+
+    contexts.handleInputEvent(InputEvent::Key(KSK::GRAVE_ACCENT, KeyAction::Press, KeyMods::NONE));
+    contexts.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE));
+
+    assert_eq!(contexts.isActionPressed("console", "close_console"), true);
+    assert_eq!(contexts.get("gameplay").unwrap().state.isStandardPressed(KSK::GRAVE_ACCENT as u64), false);
+    assert_eq!(contexts.isActionPressed("gameplay", "move_forward"), true);
+}
+
+#[test]
+fn contextReleasePassesTest()
+{
+    use crate::input::KSK;
+    use crate::event::KeyMods;
+
+    let mut contexts = ContextStack::new();
+    contexts.push(InputContext::new("gameplay", ActionMap::defaults(), ConsumeMode::Pass));
+
+    contexts.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE));
+    contexts.push(InputContext::new("menu", ActionMap::new(), ConsumeMode::All));
+    contexts.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Release, KeyMods::NONE));
+    contexts.remove("menu");
+
+    assert_eq!(contexts.isActionPressed("gameplay", "move_forward"), false);
+    assert_eq!(contexts.len(), 1);
+}
+
+#[test]
+fn contextAnalogTest()
+{
+    use crate::gamepad::KGA;
+
+    let mut contexts = ContextStack::new();
+    contexts.push(InputContext::new("gameplay", ActionMap::defaults(), ConsumeMode::Pass));
+    contexts.handleInputEvent(InputEvent::ControllerConnected(0));
+    contexts.handleInputEvent(InputEvent::CursorMoved(10.0, 10.0));
+
+    contexts.push(InputContext::new("menu", ActionMap::new(), ConsumeMode::All));
+    contexts.handleInputEvent(InputEvent::ControllerAxis(0, KGA::LeftX, 1.0));
+    contexts.handleInputEvent(InputEvent::ControllerAxis(0, KGA::LeftX, 0.0));
+    contexts.handleInputEvent(InputEvent::CursorMoved(300.0, 200.0));
+
+    // Gameplay knows where the cursor went, but did not see it move
+    let gameplay = contexts.get("gameplay").unwrap();
+    assert_eq!(gameplay.state.getCursorPos(), (300.0, 200.0));
+    assert_eq!(gameplay.state.getCursorDelta(), (0.0, 0.0));
+
+    contexts.pop();
+
+    assert_eq!(contexts.top().unwrap().state.getGamepadAxis(0, KGA::LeftX), 0.0);
+}
+
+/*************************************/
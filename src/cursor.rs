@@ -0,0 +1,140 @@
+use crate::input::InputState;
+
+// Kestrel Cursor Mode
+// Normal shows the cursor and lets it leave the window.
+// Hidden only hides it while it is over the window.
+// Captured hides and locks it to the window so motion is
+// unbounded, which is what first person cameras want.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CursorMode
+{
+    Normal,
+    Hidden,
+    Captured,
+}
+
+// Keeps track of the cursor mode the game asked for and the one
+// actually applied to the window. They differ while the window
+// is unfocused, since a captured cursor would otherwise stay
+// trapped when the player tabs out.
+pub struct CursorController
+{
+    pub requested: CursorMode,
+    pub focused: bool,
+    pub raw_motion_supported: bool,
+    // What the window was last set to, None until the first apply
+    applied: Option<CursorMode>,
+}
+
+impl CursorMode
+{
+    fn toGlfw(&self) -> glfw::CursorMode
+    {
+        return match *self
+        {
+            CursorMode::Normal =>   glfw::CursorMode::Normal,
+            CursorMode::Hidden =>   glfw::CursorMode::Hidden,
+            CursorMode::Captured => glfw::CursorMode::Disabled,
+        };
+    }
+}
+
+impl CursorController
+{
+    // Pass glfw.supports_raw_motion() so captured mode can
+    // skip the OS's pointer acceleration when it is available.
+    pub fn new(rawMotionSupported: bool) -> CursorController
+    {
+        CursorController
+        {
+            requested: CursorMode::Normal,
+            focused: true,
+            raw_motion_supported: rawMotionSupported,
+            applied: None,
+        }
+    }
+
+    pub fn setMode(&mut self, mode: CursorMode)
+    {
+        self.requested = mode;
+    }
+
+    pub fn focusChanged(&mut self, focused: bool)
+    {
+        self.focused = focused;
+    }
+
+    // The mode the window should be in right now
+    pub fn effectiveMode(&self) -> CursorMode
+    {
+        if !self.focused && self.requested == CursorMode::Captured
+        {
+            return CursorMode::Normal;
+        }
+        return self.requested;
+    }
+
+    pub fn isCaptured(&self) -> bool
+    {
+        return self.effectiveMode() == CursorMode::Captured;
+    }
+
+    // Pushes the effective mode to the window if it changed.
+    // Call once a frame after handling window events.
+    pub fn apply(&mut self, window: &mut glfw::Window, inState: &mut InputState)
+    {
+        let mode = self.effectiveMode();
+        if self.applied == Some(mode)
+        {
+            return;
+        }
+
+        window.set_cursor_mode(mode.toGlfw());
+        if self.raw_motion_supported
+        {
+            window.set_raw_mouse_motion(mode == CursorMode::Captured);
+        }
+
+        inState.setCursorCaptured(mode == CursorMode::Captured);
+        self.applied = Some(mode);
+    }
+}
+
+/*************************************/
+// Cursor tests
+
+#[test]
+fn cursorModeTest()
+{
+    let mut cursor = CursorController::new(false);
+
+    assert_eq!(cursor.effectiveMode(), CursorMode::Normal);
+
+    cursor.setMode(CursorMode::Captured);
+
+    assert_eq!(cursor.isCaptured(), true);
+}
+
+#[test]
+fn cursorFocusLossTest()
+{
+    let mut cursor = CursorController::new(true);
+
+    cursor.setMode(CursorMode::Captured);
+    cursor.focusChanged(false);
+
+    assert_eq!(cursor.effectiveMode(), CursorMode::Normal);
+    assert_eq!(cursor.requested, CursorMode::Captured);
+
+    cursor.focusChanged(true);
+
+    assert_eq!(cursor.effectiveMode(), CursorMode::Captured);
+
+    // Hidden is harmless when unfocused so it is left alone
+    cursor.setMode(CursorMode::Hidden);
+    cursor.focusChanged(false);
+
+    assert_eq!(cursor.effectiveMode(), CursorMode::Hidden);
+}
+
+/*************************************/
@@ -0,0 +1,222 @@
+use std::ops::BitOr;
+
+use glfw::{Action, Modifiers};
+
+use crate::input::{KSK, KMK, KMB, standardKeyFromGlfw, modifierKeyFromGlfw, mouseButtonFromGlfw};
+use crate::gamepad::{KGB, KGA};
+
+// Kestrel's own version of glfw::Action so nothing
+// past this module has to know which backend is in use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyAction
+{
+    Press,
+    Repeat,
+    Release,
+}
+
+// Kestrel's own copy of the glfw::Modifiers bitfield,
+// the modifiers that were down when a key or button changed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyMods
+{
+    pub bits: u8,
+}
+
+// Every input the engine understands.
+// InputState can be fed these from GLFW, from tests,
+// from a replay, or from over the network.
+// Controller events carry the slot the controller is in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent
+{
+    Key(KSK, KeyAction, KeyMods),
+    Modifier(KMK, KeyAction),
+    MouseButton(KMB, KeyAction, KeyMods),
+    CursorMoved(f64, f64),
+    Scroll(f64, f64),
+    CursorEntered(bool),
+    ControllerConnected(u8),
+    ControllerDisconnected(u8),
+    ControllerButton(u8, KGB, KeyAction),
+    ControllerAxis(u8, KGA, f32),
+    // A typed character, after the keyboard layout and IME have had their say
+    Char(char, KeyMods),
+    // Losing focus lets go of every key and button, since
+    // their releases will go to some other window
+    Focus(bool),
+    // A key with no KSK, by its platform scancode. Keyboards have
+    // keys GLFW has no Key for, like the extra key on ISO layouts.
+    Physical(i32, KeyAction),
+}
+
+// Window events the game might care about that are not input.
+// Sizes are (width, height), in screen coordinates for Resized
+// and in pixels for FramebufferResized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineEvent
+{
+    FocusChanged(bool),
+    Iconified(bool),
+    CloseRequested,
+    Resized(i32, i32),
+    FramebufferResized(i32, i32),
+}
+
+impl KeyAction
+{
+    pub fn fromGlfw(action: Action) -> KeyAction
+    {
+        return match action
+        {
+            Action::Press =>    KeyAction::Press,
+            Action::Repeat =>   KeyAction::Repeat,
+            Action::Release =>  KeyAction::Release,
+        };
+    }
+}
+
+impl KeyMods
+{
+    pub const NONE: KeyMods =       KeyMods { bits: 0 };
+    pub const SHIFT: KeyMods =      KeyMods { bits: 1 };
+    pub const CONTROL: KeyMods =    KeyMods { bits: 2 };
+    pub const ALT: KeyMods =        KeyMods { bits: 4 };
+    pub const SUPER: KeyMods =      KeyMods { bits: 8 };
+    pub const CAPS_LOCK: KeyMods =  KeyMods { bits: 16 };
+    pub const NUM_LOCK: KeyMods =   KeyMods { bits: 32 };
+
+    // Lock keys stay on for ages, so combos ignore them
+    const LOCKS: u8 = 16 | 32;
+
+    pub fn fromGlfw(mods: Modifiers) -> KeyMods
+    {
+        let mut ret = KeyMods::NONE;
+
+        if mods.contains(Modifiers::Shift)      { ret = ret | KeyMods::SHIFT; }
+        if mods.contains(Modifiers::Control)    { ret = ret | KeyMods::CONTROL; }
+        if mods.contains(Modifiers::Alt)        { ret = ret | KeyMods::ALT; }
+        if mods.contains(Modifiers::Super)      { ret = ret | KeyMods::SUPER; }
+        if mods.contains(Modifiers::CapsLock)   { ret = ret | KeyMods::CAPS_LOCK; }
+        if mods.contains(Modifiers::NumLock)    { ret = ret | KeyMods::NUM_LOCK; }
+
+        return ret;
+    }
+
+    pub fn contains(&self, other: KeyMods) -> bool
+    {
+        return self.bits & other.bits == other.bits;
+    }
+
+    // True when exactly these modifiers are down, ignoring lock keys.
+    // Ctrl+Shift+S does not match Ctrl+S.
+    pub fn matches(&self, other: KeyMods) -> bool
+    {
+        return self.bits & !KeyMods::LOCKS == other.bits & !KeyMods::LOCKS;
+    }
+}
+
+impl BitOr for KeyMods
+{
+    type Output = KeyMods;
+
+    fn bitor(self, other: KeyMods) -> KeyMods
+    {
+        return KeyMods { bits: self.bits | other.bits };
+    }
+}
+
+impl InputEvent
+{
+    // Window events that are not input, or input the engine
+    // does not track yet, come back as None.
+    pub fn fromGlfw(event: &glfw::WindowEvent) -> Option<InputEvent>
+    {
+        return match *event
+        {
+            glfw::WindowEvent::Key(key, scancode, action, mods) =>
+            {
+                if let Some(kmk) = modifierKeyFromGlfw(key)
+                {
+                    return Some(InputEvent::Modifier(kmk, KeyAction::fromGlfw(action)));
+                }
+                match standardKeyFromGlfw(key)
+                {
+                    Some(ksk) => Some(InputEvent::Key(ksk, KeyAction::fromGlfw(action), KeyMods::fromGlfw(mods))),
+                    None => Some(InputEvent::Physical(scancode as i32, KeyAction::fromGlfw(action))),
+                }
+            },
+            glfw::WindowEvent::MouseButton(button, action, mods) =>
+                mouseButtonFromGlfw(button).map(|kmb| InputEvent::MouseButton(kmb, KeyAction::fromGlfw(action), KeyMods::fromGlfw(mods))),
+            glfw::WindowEvent::CursorPos(x, y) =>       Some(InputEvent::CursorMoved(x, y)),
+            glfw::WindowEvent::Scroll(x, y) =>          Some(InputEvent::Scroll(x, y)),
+            glfw::WindowEvent::CursorEnter(entered) =>  Some(InputEvent::CursorEntered(entered)),
+            glfw::WindowEvent::Char(c) =>               Some(InputEvent::Char(c, KeyMods::NONE)),
+            glfw::WindowEvent::CharModifiers(c, mods) => Some(InputEvent::Char(c, KeyMods::fromGlfw(mods))),
+            glfw::WindowEvent::Focus(focused) =>        Some(InputEvent::Focus(focused)),
+            _ => None,
+        };
+    }
+}
+
+impl EngineEvent
+{
+    pub fn fromGlfw(event: &glfw::WindowEvent) -> Option<EngineEvent>
+    {
+        return match *event
+        {
+            glfw::WindowEvent::Focus(focused) =>                Some(EngineEvent::FocusChanged(focused)),
+            glfw::WindowEvent::Iconify(iconified) =>            Some(EngineEvent::Iconified(iconified)),
+            glfw::WindowEvent::Close =>                         Some(EngineEvent::CloseRequested),
+            glfw::WindowEvent::Size(width, height) =>           Some(EngineEvent::Resized(width, height)),
+            glfw::WindowEvent::FramebufferSize(width, height) => Some(EngineEvent::FramebufferResized(width, height)),
+            _ => None,
+        };
+    }
+}
+
+/*************************************/
+// Event tests
+
+#[test]
+fn eventFromGlfwTest()
+{
+    let key = glfw::WindowEvent::Key(glfw::Key::W, 0, Action::Press, Modifiers::empty());
+    let button = glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Release, Modifiers::Control);
+    let shift = glfw::WindowEvent::Key(glfw::Key::RightShift, 0, Action::Press, Modifiers::Shift);
+
+    assert_eq!(InputEvent::fromGlfw(&key), Some(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE)));
+    assert_eq!(InputEvent::fromGlfw(&button), Some(InputEvent::MouseButton(KMB::M1, KeyAction::Release, KeyMods::CONTROL)));
+    assert_eq!(InputEvent::fromGlfw(&shift), Some(InputEvent::Modifier(KMK::RShift, KeyAction::Press)));
+    assert_eq!(InputEvent::fromGlfw(&glfw::WindowEvent::Scroll(0.0, -1.0)), Some(InputEvent::Scroll(0.0, -1.0)));
+    assert_eq!(InputEvent::fromGlfw(&glfw::WindowEvent::Char('é')), Some(InputEvent::Char('é', KeyMods::NONE)));
+    assert_eq!(InputEvent::fromGlfw(&glfw::WindowEvent::Focus(false)), Some(InputEvent::Focus(false)));
+    assert_eq!(InputEvent::fromGlfw(&glfw::WindowEvent::Iconify(true)), None);
+
+    // The ISO key next to left shift has no Key, just a scancode
+    let iso = glfw::WindowEvent::Key(glfw::Key::Unknown, 94, Action::Press, Modifiers::empty());
+
+    assert_eq!(InputEvent::fromGlfw(&iso), Some(InputEvent::Physical(94, KeyAction::Press)));
+}
+
+#[test]
+fn engineEventFromGlfwTest()
+{
+    assert_eq!(EngineEvent::fromGlfw(&glfw::WindowEvent::Close), Some(EngineEvent::CloseRequested));
+    assert_eq!(EngineEvent::fromGlfw(&glfw::WindowEvent::FramebufferSize(1500, 1500)), Some(EngineEvent::FramebufferResized(1500, 1500)));
+    assert_eq!(EngineEvent::fromGlfw(&glfw::WindowEvent::Focus(true)), Some(EngineEvent::FocusChanged(true)));
+    assert_eq!(EngineEvent::fromGlfw(&glfw::WindowEvent::Scroll(0.0, 1.0)), None);
+}
+
+#[test]
+fn keyModsTest()
+{
+    let mods = KeyMods::fromGlfw(Modifiers::Control | Modifiers::NumLock);
+
+    assert_eq!(mods.contains(KeyMods::CONTROL), true);
+    assert_eq!(mods.matches(KeyMods::CONTROL), true);
+    assert_eq!(mods.matches(KeyMods::CONTROL | KeyMods::SHIFT), false);
+    assert_eq!(mods.contains(KeyMods::SHIFT), false);
+}
+
+/*************************************/
@@ -0,0 +1,477 @@
+use glfw::{Action, GamepadAxis, GamepadButton, JoystickId};
+
+use crate::event::{InputEvent, KeyAction};
+
+// GLFW supports 16 joysticks at once
+pub const MAX_CONTROLLERS: usize = 16;
+pub const KGB_COUNT: usize = 15;
+pub const KGA_COUNT: usize = 6;
+
+// Kestrel Gamepad Button (KGB)
+// The standard gamepad layout, laid out like an Xbox controller.
+// Other controllers get mapped onto it by GLFW's gamepad mappings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KGB
+{
+    A = 0,
+    B = 1,
+    X = 2,
+    Y = 3,
+    LeftBumper = 4,
+    RightBumper = 5,
+    Back = 6,
+    Start = 7,
+    Guide = 8,
+    LeftThumb = 9,
+    RightThumb = 10,
+    DpadUp = 11,
+    DpadRight = 12,
+    DpadDown = 13,
+    DpadLeft = 14,
+}
+
+// Kestrel Gamepad Axis (KGA)
+// Sticks go from -1 to 1, triggers rest at 0 and go to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KGA
+{
+    LeftX = 0,
+    LeftY = 1,
+    RightX = 2,
+    RightY = 3,
+    LeftTrigger = 4,
+    RightTrigger = 5,
+}
+
+const KGB_NAMES: [(KGB, &str); KGB_COUNT] =
+[
+    (KGB::A, "PadA"),
+    (KGB::B, "PadB"),
+    (KGB::X, "PadX"),
+    (KGB::Y, "PadY"),
+    (KGB::LeftBumper, "PadLeftBumper"),
+    (KGB::RightBumper, "PadRightBumper"),
+    (KGB::Back, "PadBack"),
+    (KGB::Start, "PadStart"),
+    (KGB::Guide, "PadGuide"),
+    (KGB::LeftThumb, "PadLeftThumb"),
+    (KGB::RightThumb, "PadRightThumb"),
+    (KGB::DpadUp, "PadDpadUp"),
+    (KGB::DpadRight, "PadDpadRight"),
+    (KGB::DpadDown, "PadDpadDown"),
+    (KGB::DpadLeft, "PadDpadLeft"),
+];
+
+const KGA_NAMES: [(KGA, &str); KGA_COUNT] =
+[
+    (KGA::LeftX, "PadLeftX"),
+    (KGA::LeftY, "PadLeftY"),
+    (KGA::RightX, "PadRightX"),
+    (KGA::RightY, "PadRightY"),
+    (KGA::LeftTrigger, "PadLeftTrigger"),
+    (KGA::RightTrigger, "PadRightTrigger"),
+];
+
+const GLFW_BUTTONS: [GamepadButton; KGB_COUNT] =
+[
+    GamepadButton::ButtonA,
+    GamepadButton::ButtonB,
+    GamepadButton::ButtonX,
+    GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper,
+    GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack,
+    GamepadButton::ButtonStart,
+    GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb,
+    GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp,
+    GamepadButton::ButtonDpadRight,
+    GamepadButton::ButtonDpadDown,
+    GamepadButton::ButtonDpadLeft,
+];
+
+const GLFW_AXES: [GamepadAxis; KGA_COUNT] =
+[
+    GamepadAxis::AxisLeftX,
+    GamepadAxis::AxisLeftY,
+    GamepadAxis::AxisRightX,
+    GamepadAxis::AxisRightY,
+    GamepadAxis::AxisLeftTrigger,
+    GamepadAxis::AxisRightTrigger,
+];
+
+const GLFW_JOYSTICKS: [JoystickId; MAX_CONTROLLERS] =
+[
+    JoystickId::Joystick1,
+    JoystickId::Joystick2,
+    JoystickId::Joystick3,
+    JoystickId::Joystick4,
+    JoystickId::Joystick5,
+    JoystickId::Joystick6,
+    JoystickId::Joystick7,
+    JoystickId::Joystick8,
+    JoystickId::Joystick9,
+    JoystickId::Joystick10,
+    JoystickId::Joystick11,
+    JoystickId::Joystick12,
+    JoystickId::Joystick13,
+    JoystickId::Joystick14,
+    JoystickId::Joystick15,
+    JoystickId::Joystick16,
+];
+
+// Everything InputState knows about one controller slot
+#[derive(Clone)]
+pub struct ControllerState
+{
+    pub connected: bool,
+    pub button_press: Vec<bool>,
+    pub button_just_pressed: Vec<bool>,
+    pub button_just_released: Vec<bool>,
+    pub axes: Vec<f32>,
+}
+
+// What a controller looked like the last time it was read
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControllerSnapshot
+{
+    pub name: String,
+    pub buttons: [bool; KGB_COUNT],
+    pub axes: [f32; KGA_COUNT],
+}
+
+// Anything controllers can be read from.
+// GLFW is the real one, MockControllerSource is for tests.
+pub trait ControllerSource
+{
+    // None means nothing is plugged into that slot
+    fn readController(&mut self, slot: usize) -> Option<ControllerSnapshot>;
+}
+
+// A fake set of controllers that tests can plug in, press and unplug
+pub struct MockControllerSource
+{
+    pub slots: Vec<Option<ControllerSnapshot>>,
+}
+
+// Polls a ControllerSource and turns whatever changed since last
+// time into InputEvents. Plugging and unplugging is found the same
+// way, by a slot going from empty to full or back.
+pub struct ControllerPoller
+{
+    last: Vec<Option<ControllerSnapshot>>,
+}
+
+impl KGB
+{
+    pub fn name(&self) -> &'static str
+    {
+        return KGB_NAMES[*self as usize].1;
+    }
+
+    pub fn fromName(name: &str) -> Option<KGB>
+    {
+        return KGB_NAMES.iter().find(|entry| entry.1 == name).map(|entry| entry.0);
+    }
+
+    pub fn fromIndex(index: usize) -> Option<KGB>
+    {
+        return KGB_NAMES.get(index).map(|entry| entry.0);
+    }
+}
+
+impl KGA
+{
+    pub fn name(&self) -> &'static str
+    {
+        return KGA_NAMES[*self as usize].1;
+    }
+
+    pub fn fromName(name: &str) -> Option<KGA>
+    {
+        return KGA_NAMES.iter().find(|entry| entry.1 == name).map(|entry| entry.0);
+    }
+
+    pub fn fromIndex(index: usize) -> Option<KGA>
+    {
+        return KGA_NAMES.get(index).map(|entry| entry.0);
+    }
+}
+
+impl ControllerState
+{
+    pub fn new() -> ControllerState
+    {
+        let mut ret = ControllerState
+        {
+            connected: false,
+            button_press: Vec::with_capacity(KGB_COUNT),
+            button_just_pressed: Vec::with_capacity(KGB_COUNT),
+            button_just_released: Vec::with_capacity(KGB_COUNT),
+            axes: Vec::with_capacity(KGA_COUNT),
+        };
+
+        for _ in 0..KGB_COUNT
+        {
+            ret.button_press.push(false);
+            ret.button_just_pressed.push(false);
+            ret.button_just_released.push(false);
+        }
+        for _ in 0..KGA_COUNT
+        {
+            ret.axes.push(0.0);
+        }
+
+        return ret;
+    }
+
+    // Called when the controller is unplugged, so nothing stays stuck down
+    pub fn reset(&mut self)
+    {
+        *self = ControllerState::new();
+    }
+}
+
+impl ControllerSnapshot
+{
+    pub fn new(name: &str) -> ControllerSnapshot
+    {
+        ControllerSnapshot
+        {
+            name: name.to_string(),
+            buttons: [false; KGB_COUNT],
+            axes: [0.0; KGA_COUNT],
+        }
+    }
+}
+
+// GLFW's triggers rest at -1, ours rest at 0 like everything else
+// does when a controller is unplugged or has not moved yet
+pub fn axisFromGlfw(index: usize, value: f32) -> f32
+{
+    if index == KGA::LeftTrigger as usize || index == KGA::RightTrigger as usize
+    {
+        return (value + 1.0) / 2.0;
+    }
+    return value;
+}
+
+impl ControllerSource for glfw::Glfw
+{
+    fn readController(&mut self, slot: usize) -> Option<ControllerSnapshot>
+    {
+        let joystick = self.get_joystick(*GLFW_JOYSTICKS.get(slot)?);
+        if !joystick.is_present()
+        {
+            return None;
+        }
+
+        let mut ret = ControllerSnapshot::new("");
+
+        match joystick.get_gamepad_state()
+        {
+            Some(state) =>
+            {
+                ret.name = joystick.get_gamepad_name().unwrap_or_default();
+                for (index, button) in GLFW_BUTTONS.iter().enumerate()
+                {
+                    ret.buttons[index] = state.get_button_state(*button) == Action::Press;
+                }
+                for (index, axis) in GLFW_AXES.iter().enumerate()
+                {
+                    ret.axes[index] = axisFromGlfw(index, state.get_axis(*axis));
+                }
+            },
+            // No gamepad mapping for this joystick, so its raw buttons
+            // and axes are laid onto the standard layout in order.
+            None =>
+            {
+                ret.name = joystick.get_name().unwrap_or_default();
+                for (index, button) in joystick.get_buttons().iter().take(KGB_COUNT).enumerate()
+                {
+                    ret.buttons[index] = *button != 0;
+                }
+                for (index, axis) in joystick.get_axes().iter().take(KGA_COUNT).enumerate()
+                {
+                    ret.axes[index] = axisFromGlfw(index, *axis);
+                }
+            },
+        }
+
+        return Some(ret);
+    }
+}
+
+impl MockControllerSource
+{
+    pub fn new() -> MockControllerSource
+    {
+        MockControllerSource
+        {
+            slots: vec![None; MAX_CONTROLLERS]
+        }
+    }
+
+    pub fn connect(&mut self, slot: usize, name: &str)
+    {
+        self.slots[slot] = Some(ControllerSnapshot::new(name));
+    }
+
+    pub fn disconnect(&mut self, slot: usize)
+    {
+        self.slots[slot] = None;
+    }
+
+    pub fn setButton(&mut self, slot: usize, button: KGB, pressed: bool)
+    {
+        if let Some(pad) = self.slots[slot].as_mut()
+        {
+            pad.buttons[button as usize] = pressed;
+        }
+    }
+
+    pub fn setAxis(&mut self, slot: usize, axis: KGA, value: f32)
+    {
+        if let Some(pad) = self.slots[slot].as_mut()
+        {
+            pad.axes[axis as usize] = value;
+        }
+    }
+}
+
+impl ControllerSource for MockControllerSource
+{
+    fn readController(&mut self, slot: usize) -> Option<ControllerSnapshot>
+    {
+        return self.slots.get(slot).cloned().flatten();
+    }
+}
+
+impl ControllerPoller
+{
+    pub fn new() -> ControllerPoller
+    {
+        ControllerPoller
+        {
+            last: vec![None; MAX_CONTROLLERS]
+        }
+    }
+
+    // The name the controller in slot reported, if anything is there
+    pub fn getName(&self, slot: usize) -> Option<&str>
+    {
+        return self.last.get(slot)?.as_ref().map(|pad| pad.name.as_str());
+    }
+
+    pub fn poll<S: ControllerSource>(&mut self, source: &mut S) -> Vec<InputEvent>
+    {
+        let mut ret = Vec::new();
+
+        for slot in 0..MAX_CONTROLLERS
+        {
+            let now = source.readController(slot);
+            let id = slot as u8;
+
+            match (&self.last[slot], &now)
+            {
+                (None, Some(pad)) =>
+                {
+                    ret.push(InputEvent::ControllerConnected(id));
+                    // Compared against a controller at rest, so anything
+                    // already held or tilted on connect gets sent too
+                    diffController(id, &ControllerSnapshot::new(""), pad, &mut ret);
+                },
+                (Some(_), None) =>  {ret.push(InputEvent::ControllerDisconnected(id));},
+                (Some(old), Some(pad)) => {diffController(id, old, pad, &mut ret);},
+                (None, None) => {},
+            }
+
+            self.last[slot] = now;
+        }
+
+        return ret;
+    }
+}
+
+fn diffController(id: u8, old: &ControllerSnapshot, now: &ControllerSnapshot, out: &mut Vec<InputEvent>)
+{
+    for index in 0..KGB_COUNT
+    {
+        if old.buttons[index] != now.buttons[index]
+        {
+            let action = if now.buttons[index] { KeyAction::Press } else { KeyAction::Release };
+            out.push(InputEvent::ControllerButton(id, KGB::fromIndex(index).unwrap(), action));
+        }
+    }
+    for index in 0..KGA_COUNT
+    {
+        if old.axes[index] != now.axes[index]
+        {
+            out.push(InputEvent::ControllerAxis(id, KGA::fromIndex(index).unwrap(), now.axes[index]));
+        }
+    }
+}
+
+/*************************************/
+// Gamepad tests
+
+#[test]
+fn gamepadHotplugTest()
+{
+    let mut source = MockControllerSource::new();
+    let mut poller = ControllerPoller::new();
+
+    assert_eq!(poller.poll(&mut source).len(), 0);
+
+    source.connect(2, "Test Pad");
+    let events = poller.poll(&mut source);
+
+    assert_eq!(events[0], InputEvent::ControllerConnected(2));
+    assert_eq!(poller.getName(2), Some("Test Pad"));
+
+    source.disconnect(2);
+
+    assert_eq!(poller.poll(&mut source), vec![InputEvent::ControllerDisconnected(2)]);
+    assert_eq!(poller.getName(2), None);
+}
+
+#[test]
+fn gamepadButtonAxisTest()
+{
+    let mut source = MockControllerSource::new();
+    let mut poller = ControllerPoller::new();
+
+    source.connect(0, "Test Pad");
+    poller.poll(&mut source);
+
+    source.setButton(0, KGB::A, true);
+    source.setAxis(0, KGA::LeftX, 0.5);
+
+    assert_eq!(poller.poll(&mut source), vec![
+        InputEvent::ControllerButton(0, KGB::A, KeyAction::Press),
+        InputEvent::ControllerAxis(0, KGA::LeftX, 0.5),
+    ]);
+
+    // Nothing changed, so nothing is sent
+    assert_eq!(poller.poll(&mut source).len(), 0);
+}
+
+#[test]
+fn gamepadTriggerRestTest()
+{
+    let mut source = MockControllerSource::new();
+    let mut poller = ControllerPoller::new();
+
+    assert_eq!(axisFromGlfw(KGA::LeftTrigger as usize, -1.0), 0.0);
+    assert_eq!(axisFromGlfw(KGA::RightTrigger as usize, 1.0), 1.0);
+    assert_eq!(axisFromGlfw(KGA::LeftX as usize, -1.0), -1.0);
+
+    // A fresh pad has its triggers let go, so connecting sends nothing for them
+    source.connect(0, "Test Pad");
+
+    assert_eq!(poller.poll(&mut source), vec![InputEvent::ControllerConnected(0)]);
+    assert_eq!(ControllerState::new().axes[KGA::LeftTrigger as usize], 0.0);
+}
+
+/*************************************/
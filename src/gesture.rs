@@ -0,0 +1,306 @@
+use std::time::{Duration, Instant};
+
+use crate::event::{InputEvent, KeyAction};
+use crate::input::{KMB, KMB_COUNT};
+
+// What the recognizer picks out of the mouse streams.
+// Positions are cursor positions in screen coordinates.
+// DragStart carries where the button went down, not where the
+// cursor was once it moved far enough to count as a drag.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseGesture
+{
+    // The count goes 1, 2, 3... for single, double, triple clicks
+    Click(KMB, u32, f64, f64),
+    DragStart(KMB, f64, f64),
+    DragMove(KMB, f64, f64),
+    DragEnd(KMB, f64, f64),
+    LongPress(KMB, f64, f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureSettings
+{
+    // How soon and how close the next click has to be to count up
+    pub multi_click_time: Duration,
+    pub multi_click_distance: f64,
+    // How far the cursor moves with a button down before it is a drag
+    pub drag_distance: f64,
+    // How long a button stays down without dragging to be a long press
+    pub long_press_time: Duration,
+}
+
+// Where one button is at in a gesture
+#[derive(Clone, Copy)]
+struct ButtonTrack
+{
+    down_at: Option<(Instant, f64, f64)>,
+    dragging: bool,
+    long_pressed: bool,
+    last_click: Option<(Instant, f64, f64)>,
+    click_count: u32,
+}
+
+// Turns mouse button and cursor events into clicks, drags and long
+// presses. Feed it the same events InputState gets, and call update
+// every frame so long presses fire even when nothing is moving.
+pub struct GestureRecognizer
+{
+    pub settings: GestureSettings,
+    buttons: [ButtonTrack; KMB_COUNT],
+    cursor: (f64, f64),
+}
+
+fn distance(ax: f64, ay: f64, bx: f64, by: f64) -> f64
+{
+    return ((ax - bx) * (ax - bx) + (ay - by) * (ay - by)).sqrt();
+}
+
+impl GestureSettings
+{
+    pub fn new() -> GestureSettings
+    {
+        GestureSettings
+        {
+            multi_click_time: Duration::from_millis(400),
+            multi_click_distance: 4.0,
+            drag_distance: 6.0,
+            long_press_time: Duration::from_millis(600),
+        }
+    }
+}
+
+impl ButtonTrack
+{
+    fn new() -> ButtonTrack
+    {
+        ButtonTrack
+        {
+            down_at: None,
+            dragging: false,
+            long_pressed: false,
+            last_click: None,
+            click_count: 0,
+        }
+    }
+}
+
+impl GestureRecognizer
+{
+    pub fn new() -> GestureRecognizer
+    {
+        GestureRecognizer
+        {
+            settings: GestureSettings::new(),
+            buttons: [ButtonTrack::new(); KMB_COUNT],
+            cursor: (0.0, 0.0),
+        }
+    }
+
+    pub fn isDragging(&self, button: KMB) -> bool
+    {
+        return self.buttons[button as usize].dragging;
+    }
+
+    // now should be the time the event arrived, InputState::frame_time works
+    pub fn handleInputEvent(&mut self, event: InputEvent, now: Instant) -> Vec<MouseGesture>
+    {
+        let mut ret = Vec::new();
+        let (x, y) = self.cursor;
+
+        match event
+        {
+            InputEvent::MouseButton(button, KeyAction::Press, _) =>
+            {
+                let track = &mut self.buttons[button as usize];
+                track.down_at = Some((now, x, y));
+                track.dragging = false;
+                track.long_pressed = false;
+            },
+            InputEvent::MouseButton(button, KeyAction::Release, _) =>
+            {
+                let settings = self.settings;
+                let track = &mut self.buttons[button as usize];
+                if track.down_at.is_none()
+                {
+                    return ret;
+                }
+
+                if track.dragging
+                {
+                    ret.push(MouseGesture::DragEnd(button, x, y));
+                }
+                // A long press already said what this press was
+                else if !track.long_pressed
+                {
+                    let counts = match track.last_click
+                    {
+                        Some((time, lastX, lastY)) =>
+                            now.duration_since(time) <= settings.multi_click_time
+                            && distance(x, y, lastX, lastY) <= settings.multi_click_distance,
+                        None => false,
+                    };
+                    track.click_count = if counts { track.click_count + 1 } else { 1 };
+                    track.last_click = Some((now, x, y));
+                    ret.push(MouseGesture::Click(button, track.click_count, x, y));
+                }
+
+                track.down_at = None;
+                track.dragging = false;
+                track.long_pressed = false;
+            },
+            InputEvent::CursorMoved(newX, newY) =>
+            {
+                self.cursor = (newX, newY);
+                for index in 0..KMB_COUNT
+                {
+                    let track = &mut self.buttons[index];
+                    let button = KMB::fromIndex(index).unwrap();
+                    if let Some((_, downX, downY)) = track.down_at
+                    {
+                        if !track.dragging && !track.long_pressed
+                            && distance(newX, newY, downX, downY) >= self.settings.drag_distance
+                        {
+                            track.dragging = true;
+                            // A click after a drag starts a new count
+                            track.last_click = None;
+                            ret.push(MouseGesture::DragStart(button, downX, downY));
+                        }
+                        if track.dragging
+                        {
+                            ret.push(MouseGesture::DragMove(button, newX, newY));
+                        }
+                    }
+                }
+            },
+            // The release is going to another window, so finish up now
+            InputEvent::Focus(false) =>
+            {
+                for index in 0..KMB_COUNT
+                {
+                    if self.buttons[index].dragging
+                    {
+                        ret.push(MouseGesture::DragEnd(KMB::fromIndex(index).unwrap(), x, y));
+                    }
+                    self.buttons[index] = ButtonTrack::new();
+                }
+            },
+            _ => {},
+        }
+
+        return ret;
+    }
+
+    // Fires long presses for buttons that have been down long enough
+    pub fn update(&mut self, now: Instant) -> Vec<MouseGesture>
+    {
+        let mut ret = Vec::new();
+
+        for index in 0..KMB_COUNT
+        {
+            let track = &mut self.buttons[index];
+            if let Some((time, downX, downY)) = track.down_at
+            {
+                if !track.dragging && !track.long_pressed && now.duration_since(time) >= self.settings.long_press_time
+                {
+                    track.long_pressed = true;
+                    ret.push(MouseGesture::LongPress(KMB::fromIndex(index).unwrap(), downX, downY));
+                }
+            }
+        }
+
+        return ret;
+    }
+}
+
+/*************************************/
+// Gesture tests
+
+#[test]
+fn doubleClickTest()
+{
+    use crate::event::KeyMods;
+
+    let now = Instant::now();
+    let mut gestures = GestureRecognizer::new();
+    let press = InputEvent::MouseButton(KMB::M1, KeyAction::Press, KeyMods::NONE);
+    let release = InputEvent::MouseButton(KMB::M1, KeyAction::Release, KeyMods::NONE);
+
+    gestures.handleInputEvent(InputEvent::CursorMoved(10.0, 10.0), now);
+    gestures.handleInputEvent(press, now);
+
+    assert_eq!(gestures.handleInputEvent(release, now), vec![MouseGesture::Click(KMB::M1, 1, 10.0, 10.0)]);
+
+    gestures.handleInputEvent(press, now + Duration::from_millis(150));
+
+    assert_eq!(gestures.handleInputEvent(release, now + Duration::from_millis(200)), vec![MouseGesture::Click(KMB::M1, 2, 10.0, 10.0)]);
+
+    // Too slow for a triple click
+    gestures.handleInputEvent(press, now + Duration::from_millis(1000));
+
+    assert_eq!(gestures.handleInputEvent(release, now + Duration::from_millis(1000)), vec![MouseGesture::Click(KMB::M1, 1, 10.0, 10.0)]);
+}
+
+#[test]
+fn dragTest()
+{
+    use crate::event::KeyMods;
+
+    let now = Instant::now();
+    let mut gestures = GestureRecognizer::new();
+
+    gestures.handleInputEvent(InputEvent::MouseButton(KMB::M2, KeyAction::Press, KeyMods::NONE), now);
+
+    // Under the threshold is just a shaky click
+    assert_eq!(gestures.handleInputEvent(InputEvent::CursorMoved(3.0, 0.0), now).len(), 0);
+    assert_eq!(gestures.handleInputEvent(InputEvent::CursorMoved(20.0, 0.0), now), vec![
+        MouseGesture::DragStart(KMB::M2, 0.0, 0.0),
+        MouseGesture::DragMove(KMB::M2, 20.0, 0.0),
+    ]);
+    assert_eq!(gestures.isDragging(KMB::M2), true);
+    assert_eq!(gestures.handleInputEvent(InputEvent::MouseButton(KMB::M2, KeyAction::Release, KeyMods::NONE), now),
+        vec![MouseGesture::DragEnd(KMB::M2, 20.0, 0.0)]);
+}
+
+#[test]
+fn dragBreaksClicksTest()
+{
+    use crate::event::KeyMods;
+
+    let now = Instant::now();
+    let mut gestures = GestureRecognizer::new();
+    let press = InputEvent::MouseButton(KMB::M1, KeyAction::Press, KeyMods::NONE);
+    let release = InputEvent::MouseButton(KMB::M1, KeyAction::Release, KeyMods::NONE);
+
+    gestures.handleInputEvent(press, now);
+    gestures.handleInputEvent(release, now);
+
+    // Dragged away and back, quick enough for a double click
+    gestures.handleInputEvent(press, now + Duration::from_millis(50));
+    gestures.handleInputEvent(InputEvent::CursorMoved(20.0, 0.0), now + Duration::from_millis(60));
+    gestures.handleInputEvent(InputEvent::CursorMoved(0.0, 0.0), now + Duration::from_millis(70));
+    gestures.handleInputEvent(release, now + Duration::from_millis(80));
+    gestures.handleInputEvent(press, now + Duration::from_millis(100));
+
+    assert_eq!(gestures.handleInputEvent(release, now + Duration::from_millis(110)), vec![MouseGesture::Click(KMB::M1, 1, 0.0, 0.0)]);
+}
+
+#[test]
+fn longPressTest()
+{
+    use crate::event::KeyMods;
+
+    let now = Instant::now();
+    let mut gestures = GestureRecognizer::new();
+
+    gestures.handleInputEvent(InputEvent::MouseButton(KMB::M1, KeyAction::Press, KeyMods::NONE), now);
+
+    assert_eq!(gestures.update(now + Duration::from_millis(300)).len(), 0);
+    assert_eq!(gestures.update(now + Duration::from_millis(700)), vec![MouseGesture::LongPress(KMB::M1, 0.0, 0.0)]);
+    assert_eq!(gestures.update(now + Duration::from_millis(900)).len(), 0);
+
+    // No click after a long press
+    assert_eq!(gestures.handleInputEvent(InputEvent::MouseButton(KMB::M1, KeyAction::Release, KeyMods::NONE), now).len(), 0);
+}
+
+/*************************************/
@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use crate::event::{InputEvent, KeyAction};
+use crate::input::{InputState, KSK, KMK, KMB};
+use crate::gamepad::{KGB, KGA};
+
+// One step of a scripted input sequence.
+// Check runs its function against the state and panics with
+// the name if it comes back false, so scripts can assert as
+// they go instead of only at the end.
+#[derive(Clone, Copy)]
+pub enum ScriptStep
+{
+    Event(InputEvent),
+    Frame,
+    Wait(Duration),
+    Check(&'static str, fn(&InputState) -> bool),
+}
+
+// Drives an InputState the way the main loop would, without a
+// window. Frames and time only move when the script says so,
+// which keeps held-for timings exact.
+pub struct InputHarness
+{
+    pub state: InputState,
+    pub frame: u64,
+    pub now: Instant,
+}
+
+impl InputHarness
+{
+    pub fn new() -> InputHarness
+    {
+        let now = Instant::now();
+        let mut ret = InputHarness
+        {
+            state: InputState::new(),
+            frame: 0,
+            now,
+        };
+        ret.state.beginFrame(now);
+        return ret;
+    }
+
+    // Ends this frame and starts the next at the current time
+    pub fn nextFrame(&mut self)
+    {
+        self.state.endFrame();
+        self.frame += 1;
+        self.state.beginFrame(self.now);
+    }
+
+    // Moves the clock forward, the next frame starts that much later
+    pub fn wait(&mut self, duration: Duration)
+    {
+        self.now += duration;
+    }
+
+    pub fn press(&mut self, key: KSK)
+    {
+        self.state.injectKey(key, KeyAction::Press);
+    }
+
+    pub fn release(&mut self, key: KSK)
+    {
+        self.state.injectKey(key, KeyAction::Release);
+    }
+
+    pub fn pressModifier(&mut self, key: KMK)
+    {
+        self.state.injectModifier(key, KeyAction::Press);
+    }
+
+    pub fn releaseModifier(&mut self, key: KMK)
+    {
+        self.state.injectModifier(key, KeyAction::Release);
+    }
+
+    // Down and back up within the same frame
+    pub fn click(&mut self, button: KMB)
+    {
+        self.state.injectMouseButton(button, KeyAction::Press);
+        self.state.injectMouseButton(button, KeyAction::Release);
+    }
+
+    pub fn padPress(&mut self, slot: u8, button: KGB)
+    {
+        self.state.injectGamepadButton(slot, button, KeyAction::Press);
+    }
+
+    pub fn padRelease(&mut self, slot: u8, button: KGB)
+    {
+        self.state.injectGamepadButton(slot, button, KeyAction::Release);
+    }
+
+    pub fn padAxis(&mut self, slot: u8, axis: KGA, value: f32)
+    {
+        self.state.injectGamepadAxis(slot, axis, value);
+    }
+
+    pub fn play(&mut self, script: &[ScriptStep])
+    {
+        for step in script
+        {
+            match *step
+            {
+                ScriptStep::Event(event) =>     self.state.handleInputEvent(event),
+                ScriptStep::Frame =>            self.nextFrame(),
+                ScriptStep::Wait(duration) =>   self.wait(duration),
+                ScriptStep::Check(name, check) =>
+                {
+                    if !check(&self.state)
+                    {
+                        panic!("input script check '{}' failed on frame {}", name, self.frame);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/*************************************/
+// Inject tests
+
+#[test]
+fn injectModsTest()
+{
+    use crate::event::KeyMods;
+
+    let mut harness = InputHarness::new();
+
+    harness.pressModifier(KMK::RControl);
+    harness.press(KSK::S);
+
+    assert_eq!(harness.state.isComboPressed(KSK::S as u64, KeyMods::CONTROL), true);
+
+    harness.padPress(0, KGB::B);
+
+    assert_eq!(harness.state.isGamepadButtonPressed(0, KGB::B), true);
+
+    // There is no slot 16, so nothing happens
+    harness.padPress(16, KGB::B);
+
+    assert_eq!(harness.state.isGamepadButtonPressed(16, KGB::B), false);
+}
+
+#[test]
+fn injectScriptTest()
+{
+    use crate::event::KeyMods;
+
+    let mut harness = InputHarness::new();
+
+    harness.play(&[
+        ScriptStep::Event(InputEvent::Key(KSK::E, KeyAction::Press, KeyMods::NONE)),
+        ScriptStep::Check("e just pressed", |state| state.isStandardJustPressed(KSK::E as u64)),
+        ScriptStep::Frame,
+        ScriptStep::Wait(Duration::from_millis(600)),
+        ScriptStep::Frame,
+        ScriptStep::Check("e no longer just pressed", |state| !state.isStandardJustPressed(KSK::E as u64)),
+        ScriptStep::Check("e held half a second", |state| state.isStandardHeldFor(KSK::E as u64, Duration::from_millis(500))),
+        ScriptStep::Event(InputEvent::Key(KSK::E, KeyAction::Release, KeyMods::NONE)),
+        ScriptStep::Check("e released", |state| state.isStandardJustReleased(KSK::E as u64)),
+    ]);
+
+    assert_eq!(harness.frame, 2);
+}
+
+#[test]
+#[should_panic(expected = "never true")]
+fn injectScriptFailTest()
+{
+    let mut harness = InputHarness::new();
+
+    harness.click(KMB::M1);
+    harness.play(&[ScriptStep::Check("never true", |state| state.isMouseButtonPressed(KMB::M1 as u16))]);
+}
+
+/*************************************/
@@ -1,10 +1,21 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
 use num_traits::WrappingShl;
 use glfw::{Action, Context, Key, MouseButton};
 
+use crate::event::{InputEvent, KeyAction, KeyMods};
+use crate::gamepad::{ControllerState, MAX_CONTROLLERS, KGB, KGA, KGB_COUNT};
+use crate::axis::{Axis1D, Axis2D};
+use crate::combo::{InputHistory, ComboInput};
+use crate::access::{RepeatSettings, StickyModifiers};
+use crate::layout::KeyLayout;
+
 
 // create consts for each key to properly modify the inputstate unsigned integers
 
 // Input States go here
+#[derive(Clone)]
 pub struct InputState
 {
     // Perhaps instead of keeping bools for each control
@@ -32,18 +43,85 @@ pub struct InputState
     pub mouse_button_press: Vec<bool>,
     pub mouse_button_held: Vec<bool>,
 
-
-    pub w_Active: bool,
-    pub a_Active: bool,
-    pub s_Active: bool,
-    pub d_Active: bool,
-    pub space_Active: bool,
+    // Edge triggered state, only true for the frame
+    // the key or button changed on. Cleared by endFrame.
+    pub standard_keys_just_pressed: Vec<bool>,
+    pub standard_keys_just_released: Vec<bool>,
+    pub modifier_keys_just_pressed: Vec<bool>,
+    pub modifier_keys_just_released: Vec<bool>,
+    pub mouse_button_just_pressed: Vec<bool>,
+    pub mouse_button_just_released: Vec<bool>,
+
+    // When each key or button went down, used by the heldFor queries
+    pub standard_keys_pressed_at: Vec<Option<Instant>>,
+    pub modifier_keys_pressed_at: Vec<Option<Instant>>,
+    pub mouse_button_pressed_at: Vec<Option<Instant>>,
+
+    // The modifiers carried by the last key or mouse event,
+    // and the modifiers that were down when each key or button
+    // went down. The second is what makes Ctrl+S work.
+    pub key_mods: KeyMods,
+    pub standard_keys_mods: Vec<KeyMods>,
+    pub mouse_button_mods: Vec<KeyMods>,
+
+    // Cursor position in screen coordinates, relative to the top left
+    // of the window, and how far it moved since the last endFrame
+    pub cursor_x: f64,
+    pub cursor_y: f64,
+    pub cursor_dx: f64,
+    pub cursor_dy: f64,
+    pub cursor_in_window: bool,
+    // While captured the cursor is hidden and locked to the window,
+    // so only the deltas mean anything
+    pub cursor_captured: bool,
+    // The first position after entering has nothing to diff against
+    cursor_known: bool,
+
+    // Scroll offsets summed since startup, and just for this frame
+    pub scroll_x: f64,
+    pub scroll_y: f64,
+    pub scroll_dx: f64,
+    pub scroll_dy: f64,
+
+    // Accessibility options. With repeat set, held and repeated
+    // come from the engine's timing instead of the OS's, for every
+    // player and action alike. See ActionRepeats for per-action timing.
+    pub sticky: StickyModifiers,
+    pub repeat: Option<RepeatSettings>,
+    // True on frames where a held key repeats
+    pub standard_keys_repeated: Vec<bool>,
+    standard_keys_repeat_count: Vec<u32>,
+
+    // Scancodes of keys with no KSK that are down, see InputEvent::Physical
+    pub physical_keys_press: HashSet<i32>,
+    pub physical_keys_held: HashSet<i32>,
+    physical_keys_pressed_at: HashMap<i32, Instant>,
+
+    // What is printed on each key, for bindings by label
+    pub layout: KeyLayout,
+
+    // One entry per controller slot, connected or not
+    pub controllers: Vec<ControllerState>,
+
+    // Recent presses, for ComboMatcher to look through
+    pub history: InputHistory,
+
+    // Set by beginFrame. Every event handled during
+    // a frame is stamped with this time.
+    pub frame_time: Instant,
 }
 
+// How many KSKs there are, sizes the standard key storage
+pub const KSK_COUNT: usize = 112;
+
+// How many KMBs there are
+pub const KMB_COUNT: usize = 10;
+
 // Kestrel Standard Key (KSK)
 // The "shift" of each key
 // When key is A, shift 0 times 
 // and check if the 0th bit is set
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum KSK
 {
     A = 0,
@@ -92,18 +170,90 @@ pub enum KSK
     NUMPAD_SEVEN = 43,
     NUMPAD_EIGHT = 44,
     NUMPAD_NINE = 45,
+    SPACE = 46,
+    APOSTROPHE = 47,
+    COMMA = 48,
+    MINUS = 49,
+    PERIOD = 50,
+    SLASH = 51,
+    SEMICOLON = 52,
+    EQUAL = 53,
+    LEFT_BRACKET = 54,
+    BACKSLASH = 55,
+    RIGHT_BRACKET = 56,
+    GRAVE_ACCENT = 57,
+    WORLD_ONE = 58,
+    WORLD_TWO = 59,
+    ESCAPE = 60,
+    ENTER = 61,
+    TAB = 62,
+    BACKSPACE = 63,
+    INSERT = 64,
+    DELETE = 65,
+    HOME = 66,
+    END = 67,
+    PAGE_UP = 68,
+    PAGE_DOWN = 69,
+    UP = 70,
+    DOWN = 71,
+    LEFT = 72,
+    RIGHT = 73,
+    CAPS_LOCK = 74,
+    SCROLL_LOCK = 75,
+    NUM_LOCK = 76,
+    PRINT_SCREEN = 77,
+    PAUSE = 78,
+    MENU = 79,
+    F1 = 80,
+    F2 = 81,
+    F3 = 82,
+    F4 = 83,
+    F5 = 84,
+    F6 = 85,
+    F7 = 86,
+    F8 = 87,
+    F9 = 88,
+    F10 = 89,
+    F11 = 90,
+    F12 = 91,
+    F13 = 92,
+    F14 = 93,
+    F15 = 94,
+    F16 = 95,
+    F17 = 96,
+    F18 = 97,
+    F19 = 98,
+    F20 = 99,
+    F21 = 100,
+    F22 = 101,
+    F23 = 102,
+    F24 = 103,
+    F25 = 104,
+    NUMPAD_DECIMAL = 105,
+    NUMPAD_DIVIDE = 106,
+    NUMPAD_MULTIPLY = 107,
+    NUMPAD_SUBTRACT = 108,
+    NUMPAD_ADD = 109,
+    NUMPAD_ENTER = 110,
+    NUMPAD_EQUAL = 111,
 }
 
 // Kestrel Modifier Key (KMK)
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum KMK
 {
     LShift = 0,
     RShift = 1,
     LControl = 2,
     RControl = 3,
+    LAlt = 4,
+    RAlt = 5,
+    LSuper = 6,
+    RSuper = 7,
 }
 
 // Kestrel Mouse Buttons (KMB)
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum KMB
 {
     M1 = 0,
@@ -118,6 +268,204 @@ pub enum KMB
     M10 = 9,
 }
 
+// Names used when reading and writing bindings files.
+// Each entry has to line up with its enum above.
+pub const KSK_NAMES: [(KSK, &str); KSK_COUNT] =
+[
+    (KSK::A, "A"),
+    (KSK::B, "B"),
+    (KSK::C, "C"),
+    (KSK::D, "D"),
+    (KSK::E, "E"),
+    (KSK::F, "F"),
+    (KSK::G, "G"),
+    (KSK::H, "H"),
+    (KSK::I, "I"),
+    (KSK::J, "J"),
+    (KSK::K, "K"),
+    (KSK::L, "L"),
+    (KSK::M, "M"),
+    (KSK::N, "N"),
+    (KSK::O, "O"),
+    (KSK::P, "P"),
+    (KSK::Q, "Q"),
+    (KSK::R, "R"),
+    (KSK::S, "S"),
+    (KSK::T, "T"),
+    (KSK::U, "U"),
+    (KSK::V, "V"),
+    (KSK::W, "W"),
+    (KSK::X, "X"),
+    (KSK::Y, "Y"),
+    (KSK::Z, "Z"),
+    (KSK::ZERO, "ZERO"),
+    (KSK::ONE, "ONE"),
+    (KSK::TWO, "TWO"),
+    (KSK::THREE, "THREE"),
+    (KSK::FOUR, "FOUR"),
+    (KSK::FIVE, "FIVE"),
+    (KSK::SIX, "SIX"),
+    (KSK::SEVEN, "SEVEN"),
+    (KSK::EIGHT, "EIGHT"),
+    (KSK::NINE, "NINE"),
+    (KSK::NUMPAD_ZERO, "NUMPAD_ZERO"),
+    (KSK::NUMPAD_ONE, "NUMPAD_ONE"),
+    (KSK::NUMPAD_TWO, "NUMPAD_TWO"),
+    (KSK::NUMPAD_THREE, "NUMPAD_THREE"),
+    (KSK::NUMPAD_FOUR, "NUMPAD_FOUR"),
+    (KSK::NUMPAD_FIVE, "NUMPAD_FIVE"),
+    (KSK::NUMPAD_SIX, "NUMPAD_SIX"),
+    (KSK::NUMPAD_SEVEN, "NUMPAD_SEVEN"),
+    (KSK::NUMPAD_EIGHT, "NUMPAD_EIGHT"),
+    (KSK::NUMPAD_NINE, "NUMPAD_NINE"),
+    (KSK::SPACE, "SPACE"),
+    (KSK::APOSTROPHE, "APOSTROPHE"),
+    (KSK::COMMA, "COMMA"),
+    (KSK::MINUS, "MINUS"),
+    (KSK::PERIOD, "PERIOD"),
+    (KSK::SLASH, "SLASH"),
+    (KSK::SEMICOLON, "SEMICOLON"),
+    (KSK::EQUAL, "EQUAL"),
+    (KSK::LEFT_BRACKET, "LEFT_BRACKET"),
+    (KSK::BACKSLASH, "BACKSLASH"),
+    (KSK::RIGHT_BRACKET, "RIGHT_BRACKET"),
+    (KSK::GRAVE_ACCENT, "GRAVE_ACCENT"),
+    (KSK::WORLD_ONE, "WORLD_ONE"),
+    (KSK::WORLD_TWO, "WORLD_TWO"),
+    (KSK::ESCAPE, "ESCAPE"),
+    (KSK::ENTER, "ENTER"),
+    (KSK::TAB, "TAB"),
+    (KSK::BACKSPACE, "BACKSPACE"),
+    (KSK::INSERT, "INSERT"),
+    (KSK::DELETE, "DELETE"),
+    (KSK::HOME, "HOME"),
+    (KSK::END, "END"),
+    (KSK::PAGE_UP, "PAGE_UP"),
+    (KSK::PAGE_DOWN, "PAGE_DOWN"),
+    (KSK::UP, "UP"),
+    (KSK::DOWN, "DOWN"),
+    (KSK::LEFT, "LEFT"),
+    (KSK::RIGHT, "RIGHT"),
+    (KSK::CAPS_LOCK, "CAPS_LOCK"),
+    (KSK::SCROLL_LOCK, "SCROLL_LOCK"),
+    (KSK::NUM_LOCK, "NUM_LOCK"),
+    (KSK::PRINT_SCREEN, "PRINT_SCREEN"),
+    (KSK::PAUSE, "PAUSE"),
+    (KSK::MENU, "MENU"),
+    (KSK::F1, "F1"),
+    (KSK::F2, "F2"),
+    (KSK::F3, "F3"),
+    (KSK::F4, "F4"),
+    (KSK::F5, "F5"),
+    (KSK::F6, "F6"),
+    (KSK::F7, "F7"),
+    (KSK::F8, "F8"),
+    (KSK::F9, "F9"),
+    (KSK::F10, "F10"),
+    (KSK::F11, "F11"),
+    (KSK::F12, "F12"),
+    (KSK::F13, "F13"),
+    (KSK::F14, "F14"),
+    (KSK::F15, "F15"),
+    (KSK::F16, "F16"),
+    (KSK::F17, "F17"),
+    (KSK::F18, "F18"),
+    (KSK::F19, "F19"),
+    (KSK::F20, "F20"),
+    (KSK::F21, "F21"),
+    (KSK::F22, "F22"),
+    (KSK::F23, "F23"),
+    (KSK::F24, "F24"),
+    (KSK::F25, "F25"),
+    (KSK::NUMPAD_DECIMAL, "NUMPAD_DECIMAL"),
+    (KSK::NUMPAD_DIVIDE, "NUMPAD_DIVIDE"),
+    (KSK::NUMPAD_MULTIPLY, "NUMPAD_MULTIPLY"),
+    (KSK::NUMPAD_SUBTRACT, "NUMPAD_SUBTRACT"),
+    (KSK::NUMPAD_ADD, "NUMPAD_ADD"),
+    (KSK::NUMPAD_ENTER, "NUMPAD_ENTER"),
+    (KSK::NUMPAD_EQUAL, "NUMPAD_EQUAL"),
+];
+
+const KMK_NAMES: [(KMK, &str); 8] =
+[
+    (KMK::LShift, "LShift"),
+    (KMK::RShift, "RShift"),
+    (KMK::LControl, "LControl"),
+    (KMK::RControl, "RControl"),
+    (KMK::LAlt, "LAlt"),
+    (KMK::RAlt, "RAlt"),
+    (KMK::LSuper, "LSuper"),
+    (KMK::RSuper, "RSuper"),
+];
+
+const KMB_NAMES: [(KMB, &str); KMB_COUNT] =
+[
+    (KMB::M1, "M1"),
+    (KMB::M2, "M2"),
+    (KMB::M3, "M3"),
+    (KMB::M4, "M4"),
+    (KMB::M5, "M5"),
+    (KMB::M6, "M6"),
+    (KMB::M7, "M7"),
+    (KMB::M8, "M8"),
+    (KMB::M9, "M9"),
+    (KMB::M10, "M10"),
+];
+
+impl KSK
+{
+    pub fn name(&self) -> &'static str
+    {
+        return KSK_NAMES[*self as usize].1;
+    }
+
+    pub fn fromName(name: &str) -> Option<KSK>
+    {
+        return KSK_NAMES.iter().find(|entry| entry.1 == name).map(|entry| entry.0);
+    }
+
+    pub fn fromIndex(index: usize) -> Option<KSK>
+    {
+        return KSK_NAMES.get(index).map(|entry| entry.0);
+    }
+}
+
+impl KMK
+{
+    pub fn name(&self) -> &'static str
+    {
+        return KMK_NAMES[*self as usize].1;
+    }
+
+    pub fn fromName(name: &str) -> Option<KMK>
+    {
+        return KMK_NAMES.iter().find(|entry| entry.1 == name).map(|entry| entry.0);
+    }
+
+    pub fn fromIndex(index: usize) -> Option<KMK>
+    {
+        return KMK_NAMES.get(index).map(|entry| entry.0);
+    }
+}
+
+impl KMB
+{
+    pub fn name(&self) -> &'static str
+    {
+        return KMB_NAMES[*self as usize].1;
+    }
+
+    pub fn fromName(name: &str) -> Option<KMB>
+    {
+        return KMB_NAMES.iter().find(|entry| entry.1 == name).map(|entry| entry.0);
+    }
+
+    pub fn fromIndex(index: usize) -> Option<KMB>
+    {
+        return KMB_NAMES.get(index).map(|entry| entry.0);
+    }
+}
+
 impl InputState
 {
     pub fn new() -> InputState
@@ -131,72 +479,659 @@ impl InputState
             // mouse_button_press: 0,
             // mouse_button_held: 0,
 
-            standard_keys_press: Vec::with_capacity(64),
-            standard_keys_held: Vec::with_capacity(64),
+            standard_keys_press: Vec::with_capacity(KSK_COUNT),
+            standard_keys_held: Vec::with_capacity(KSK_COUNT),
             modifier_keys_press: Vec::with_capacity(32),
             modifier_keys_held: Vec::with_capacity(32),
             mouse_button_press: Vec::with_capacity(32),
             mouse_button_held: Vec::with_capacity(32),
 
-            w_Active: false,
-            a_Active: false,
-            s_Active: false,
-            d_Active: false,
-            space_Active: false,
+            standard_keys_just_pressed: Vec::with_capacity(KSK_COUNT),
+            standard_keys_just_released: Vec::with_capacity(KSK_COUNT),
+            modifier_keys_just_pressed: Vec::with_capacity(32),
+            modifier_keys_just_released: Vec::with_capacity(32),
+            mouse_button_just_pressed: Vec::with_capacity(32),
+            mouse_button_just_released: Vec::with_capacity(32),
+
+            standard_keys_pressed_at: Vec::with_capacity(KSK_COUNT),
+            modifier_keys_pressed_at: Vec::with_capacity(32),
+            mouse_button_pressed_at: Vec::with_capacity(32),
+
+            key_mods: KeyMods::NONE,
+            standard_keys_mods: Vec::with_capacity(KSK_COUNT),
+            mouse_button_mods: Vec::with_capacity(32),
+
+            cursor_x: 0.0,
+            cursor_y: 0.0,
+            cursor_dx: 0.0,
+            cursor_dy: 0.0,
+            cursor_in_window: false,
+            cursor_captured: false,
+            cursor_known: false,
+
+            scroll_x: 0.0,
+            scroll_y: 0.0,
+            scroll_dx: 0.0,
+            scroll_dy: 0.0,
+
+            sticky: StickyModifiers::new(),
+            repeat: None,
+            standard_keys_repeated: vec![false; KSK_COUNT],
+            standard_keys_repeat_count: vec![0; KSK_COUNT],
+
+            physical_keys_press: HashSet::new(),
+            physical_keys_held: HashSet::new(),
+            physical_keys_pressed_at: HashMap::new(),
+
+            layout: KeyLayout::us(),
+
+            controllers: Vec::with_capacity(MAX_CONTROLLERS),
+
+            history: InputHistory::new(),
+
+            frame_time: Instant::now(),
         };
 
-        for _ in 0..32
+        for _ in 0..KSK_COUNT
         {
             ret.standard_keys_press.push(false);
             ret.standard_keys_held.push(false);
+            ret.standard_keys_just_pressed.push(false);
+            ret.standard_keys_just_released.push(false);
+            ret.standard_keys_pressed_at.push(None);
+            ret.standard_keys_mods.push(KeyMods::NONE);
+        }
+        for _ in 0..32
+        {
             ret.modifier_keys_press.push(false);
             ret.modifier_keys_held.push(false);
             ret.mouse_button_press.push(false);
             ret.mouse_button_held.push(false);
+
+            ret.modifier_keys_just_pressed.push(false);
+            ret.modifier_keys_just_released.push(false);
+            ret.mouse_button_just_pressed.push(false);
+            ret.mouse_button_just_released.push(false);
+
+            ret.modifier_keys_pressed_at.push(None);
+            ret.mouse_button_pressed_at.push(None);
+            ret.mouse_button_mods.push(KeyMods::NONE);
         }
-        for _ in 32..64
+        for _ in 0..MAX_CONTROLLERS
         {
-            ret.standard_keys_press.push(false);
-            ret.standard_keys_held.push(false);
+            ret.controllers.push(ControllerState::new());
         }
 
         return ret;
     }
 
-    pub fn isStandardPressed(&mut self, key: u64) -> bool
+    pub fn isStandardPressed(&self, key: u64) -> bool
     {
         return self.standard_keys_press[key as usize];
     }
 
-    pub fn isStandardHeld(&mut self, key: u64) -> bool
+    pub fn isStandardHeld(&self, key: u64) -> bool
     {
         return self.standard_keys_held[key as usize];
     }
 
-    pub fn isModifierPressed(&mut self, key: u32) -> bool
+    pub fn isModifierPressed(&self, key: u32) -> bool
     {
         return self.modifier_keys_press[key as usize];
     }
 
-    pub fn isModifierHeld(&mut self, key: u32) -> bool
+    pub fn isModifierHeld(&self, key: u32) -> bool
     {
         return self.modifier_keys_held[key as usize];
     }
 
-    pub fn isMouseButtonPressed(&mut self, key: u16) -> bool
+    pub fn isMouseButtonPressed(&self, key: u16) -> bool
     {
         return self.mouse_button_press[key as usize];
     }
     
-    pub fn isMouseButtonHeld(&mut self, key: u16) -> bool
+    pub fn isMouseButtonHeld(&self, key: u16) -> bool
     {
         return self.mouse_button_held[key as usize];
     }
+
+    // Call once at the top of each frame, before any events are handled
+    pub fn beginFrame(&mut self, now: Instant)
+    {
+        self.frame_time = now;
+
+        if let Some(settings) = self.repeat
+        {
+            for key in 0..KSK_COUNT
+            {
+                if let Some(pressedAt) = self.standard_keys_pressed_at[key]
+                {
+                    let repeats = settings.repeatsAfter(now.duration_since(pressedAt));
+                    if repeats > self.standard_keys_repeat_count[key]
+                    {
+                        self.standard_keys_held[key] = true;
+                        self.standard_keys_repeated[key] = true;
+                        self.standard_keys_repeat_count[key] = repeats;
+                    }
+                }
+            }
+            for (scancode, pressedAt) in self.physical_keys_pressed_at.iter()
+            {
+                if settings.repeatsAfter(now.duration_since(*pressedAt)) > 0
+                {
+                    self.physical_keys_held.insert(*scancode);
+                }
+            }
+            // Modifiers only ever get held, they have nothing to repeat
+            for key in 0..self.modifier_keys_pressed_at.len()
+            {
+                if let Some(pressedAt) = self.modifier_keys_pressed_at[key]
+                {
+                    if settings.repeatsAfter(now.duration_since(pressedAt)) > 0
+                    {
+                        self.modifier_keys_held[key] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Call once at the bottom of each frame, after gameplay
+    // code is done asking about this frame's input.
+    pub fn endFrame(&mut self)
+    {
+        for flag in self.standard_keys_just_pressed.iter_mut().chain(self.standard_keys_just_released.iter_mut())
+        {
+            *flag = false;
+        }
+        for flag in self.standard_keys_repeated.iter_mut()
+        {
+            *flag = false;
+        }
+        for flag in self.modifier_keys_just_pressed.iter_mut().chain(self.modifier_keys_just_released.iter_mut())
+        {
+            *flag = false;
+        }
+        for flag in self.mouse_button_just_pressed.iter_mut().chain(self.mouse_button_just_released.iter_mut())
+        {
+            *flag = false;
+        }
+
+        self.cursor_dx = 0.0;
+        self.cursor_dy = 0.0;
+        self.scroll_dx = 0.0;
+        self.scroll_dy = 0.0;
+
+        for pad in self.controllers.iter_mut()
+        {
+            for flag in pad.button_just_pressed.iter_mut().chain(pad.button_just_released.iter_mut())
+            {
+                *flag = false;
+            }
+        }
+    }
+
+    pub fn isStandardJustPressed(&self, key: u64) -> bool
+    {
+        return self.standard_keys_just_pressed[key as usize];
+    }
+
+    pub fn isStandardJustReleased(&self, key: u64) -> bool
+    {
+        return self.standard_keys_just_released[key as usize];
+    }
+
+    pub fn isStandardRepeated(&self, key: u64) -> bool
+    {
+        return self.standard_keys_repeated[key as usize];
+    }
+
+    pub fn isStandardHeldFor(&self, key: u64, duration: Duration) -> bool
+    {
+        return heldFor(self.standard_keys_pressed_at[key as usize], self.frame_time, duration);
+    }
+
+    pub fn isModifierJustPressed(&self, key: u32) -> bool
+    {
+        return self.modifier_keys_just_pressed[key as usize];
+    }
+
+    pub fn isModifierJustReleased(&self, key: u32) -> bool
+    {
+        return self.modifier_keys_just_released[key as usize];
+    }
+
+    pub fn isModifierHeldFor(&self, key: u32, duration: Duration) -> bool
+    {
+        return heldFor(self.modifier_keys_pressed_at[key as usize], self.frame_time, duration);
+    }
+
+    pub fn isMouseButtonJustPressed(&self, key: u16) -> bool
+    {
+        return self.mouse_button_just_pressed[key as usize];
+    }
+
+    pub fn isMouseButtonJustReleased(&self, key: u16) -> bool
+    {
+        return self.mouse_button_just_released[key as usize];
+    }
+
+    pub fn isMouseButtonHeldFor(&self, key: u16, duration: Duration) -> bool
+    {
+        return heldFor(self.mouse_button_pressed_at[key as usize], self.frame_time, duration);
+    }
+
+    pub fn getCursorPos(&self) -> (f64, f64)
+    {
+        return (self.cursor_x, self.cursor_y);
+    }
+
+    pub fn getCursorDelta(&self) -> (f64, f64)
+    {
+        return (self.cursor_dx, self.cursor_dy);
+    }
+
+    pub fn isCursorInWindow(&self) -> bool
+    {
+        return self.cursor_in_window;
+    }
+
+    pub fn isCursorCaptured(&self) -> bool
+    {
+        return self.cursor_captured;
+    }
+
+    // Moves the cursor without it counting as motion
+    pub fn placeCursor(&mut self, x: f64, y: f64)
+    {
+        self.cursor_x = x;
+        self.cursor_y = y;
+        self.cursor_known = true;
+    }
+
+    // Switching cursor modes makes GLFW jump the cursor, so the
+    // next position is treated as a fresh start rather than motion.
+    pub fn setCursorCaptured(&mut self, captured: bool)
+    {
+        self.cursor_captured = captured;
+        self.cursor_known = false;
+    }
+
+    pub fn setLayout(&mut self, layout: KeyLayout)
+    {
+        self.layout = layout;
+    }
+
+    pub fn getScroll(&self) -> (f64, f64)
+    {
+        return (self.scroll_x, self.scroll_y);
+    }
+
+    pub fn getScrollDelta(&self) -> (f64, f64)
+    {
+        return (self.scroll_dx, self.scroll_dy);
+    }
+
+    pub fn getMods(&self) -> KeyMods
+    {
+        return self.key_mods;
+    }
+
+    // True while key is down and it went down with exactly mods held,
+    // e.g. isComboPressed(KSK::S as u64, KeyMods::CONTROL) for Ctrl+S
+    pub fn isComboPressed(&self, key: u64, mods: KeyMods) -> bool
+    {
+        return self.standard_keys_press[key as usize] && self.standard_keys_mods[key as usize].matches(mods);
+    }
+
+    pub fn isComboJustPressed(&self, key: u64, mods: KeyMods) -> bool
+    {
+        return self.standard_keys_just_pressed[key as usize] && self.standard_keys_mods[key as usize].matches(mods);
+    }
+
+    pub fn isMouseComboPressed(&self, key: u16, mods: KeyMods) -> bool
+    {
+        return self.mouse_button_press[key as usize] && self.mouse_button_mods[key as usize].matches(mods);
+    }
+
+    // For keys with no KSK, by scancode
+    pub fn isPhysicalPressed(&self, scancode: i32) -> bool
+    {
+        return self.physical_keys_press.contains(&scancode);
+    }
+
+    pub fn isPhysicalHeld(&self, scancode: i32) -> bool
+    {
+        return self.physical_keys_held.contains(&scancode);
+    }
+
+    pub fn isControllerConnected(&self, slot: usize) -> bool
+    {
+        return self.controllers.get(slot).map(|pad| pad.connected).unwrap_or(false);
+    }
+
+    // Slots with a controller in them, lowest first
+    pub fn connectedControllers(&self) -> Vec<usize>
+    {
+        return (0..self.controllers.len()).filter(|slot| self.controllers[*slot].connected).collect();
+    }
+
+    pub fn isGamepadButtonPressed(&self, slot: usize, button: KGB) -> bool
+    {
+        return self.controllers.get(slot).map(|pad| pad.button_press[button as usize]).unwrap_or(false);
+    }
+
+    pub fn isGamepadButtonJustPressed(&self, slot: usize, button: KGB) -> bool
+    {
+        return self.controllers.get(slot).map(|pad| pad.button_just_pressed[button as usize]).unwrap_or(false);
+    }
+
+    pub fn isGamepadButtonJustReleased(&self, slot: usize, button: KGB) -> bool
+    {
+        return self.controllers.get(slot).map(|pad| pad.button_just_released[button as usize]).unwrap_or(false);
+    }
+
+    pub fn getGamepadAxis(&self, slot: usize, axis: KGA) -> f32
+    {
+        return self.controllers.get(slot).map(|pad| pad.axes[axis as usize]).unwrap_or(0.0);
+    }
+
+    // Virtual axes, e.g. getAxis2D(&Axis2D::wasd()) for movement
+    pub fn getAxis(&self, axis: &Axis1D) -> f32
+    {
+        return axis.value(self);
+    }
+
+    pub fn getAxis2D(&self, axis: &Axis2D) -> (f32, f32)
+    {
+        return axis.value(self);
+    }
+
+    // Lets go of every key and button, marking the ones that were
+    // down as just released so gameplay notices. Controllers are
+    // left alone since they keep working without focus.
+    pub fn releaseAll(&mut self)
+    {
+        for key in KSK_NAMES.iter().map(|entry| entry.0)
+        {
+            applyStandardKey(self, key, KeyAction::Release);
+        }
+        for key in KMK_NAMES.iter().map(|entry| entry.0)
+        {
+            applyModifierKey(self, key, KeyAction::Release);
+        }
+        for button in KMB_NAMES.iter().map(|entry| entry.0)
+        {
+            applyMouseButton(self, button, KeyAction::Release);
+        }
+        self.physical_keys_press.clear();
+        self.physical_keys_held.clear();
+        self.physical_keys_pressed_at.clear();
+        self.key_mods = KeyMods::NONE;
+
+        // Nothing from before focus was lost carries over to the
+        // keys pressed after it comes back
+        self.sticky.reset();
+        self.history.clear();
+        for count in self.standard_keys_repeat_count.iter_mut()
+        {
+            *count = 0;
+        }
+        for flag in self.standard_keys_repeated.iter_mut()
+        {
+            *flag = false;
+        }
+    }
+
+    ////////////////////////////////////////////////
+    // Synthetic input
+    // For tests, bots and scripted demos. These go through
+    // handleInputEvent just like real input does, so everything
+    // downstream behaves the same. Keys and buttons pick up
+    // whichever modifier keys are currently down.
+
+    // The modifier bits a real key event would carry right now
+    pub fn heldMods(&self) -> KeyMods
+    {
+        let mut ret = KeyMods::NONE;
+        let down = |a: KMK, b: KMK| self.modifier_keys_press[a as usize] || self.modifier_keys_press[b as usize];
+
+        if down(KMK::LShift, KMK::RShift)       { ret = ret | KeyMods::SHIFT; }
+        if down(KMK::LControl, KMK::RControl)   { ret = ret | KeyMods::CONTROL; }
+        if down(KMK::LAlt, KMK::RAlt)           { ret = ret | KeyMods::ALT; }
+        if down(KMK::LSuper, KMK::RSuper)       { ret = ret | KeyMods::SUPER; }
+
+        return ret;
+    }
+
+    pub fn injectKey(&mut self, key: KSK, action: KeyAction)
+    {
+        let mods = self.heldMods();
+        self.handleInputEvent(InputEvent::Key(key, action, mods));
+    }
+
+    pub fn injectModifier(&mut self, key: KMK, action: KeyAction)
+    {
+        self.handleInputEvent(InputEvent::Modifier(key, action));
+    }
+
+    pub fn injectMouseButton(&mut self, button: KMB, action: KeyAction)
+    {
+        let mods = self.heldMods();
+        self.handleInputEvent(InputEvent::MouseButton(button, action, mods));
+    }
+
+    pub fn injectCursor(&mut self, x: f64, y: f64)
+    {
+        self.handleInputEvent(InputEvent::CursorMoved(x, y));
+    }
+
+    pub fn injectScroll(&mut self, x: f64, y: f64)
+    {
+        self.handleInputEvent(InputEvent::Scroll(x, y));
+    }
+
+    pub fn injectGamepadButton(&mut self, slot: u8, button: KGB, action: KeyAction)
+    {
+        self.handleInputEvent(InputEvent::ControllerButton(slot, button, action));
+    }
+
+    pub fn injectGamepadAxis(&mut self, slot: u8, axis: KGA, value: f32)
+    {
+        self.handleInputEvent(InputEvent::ControllerAxis(slot, axis, value));
+    }
+
+    pub fn injectControllerConnected(&mut self, slot: u8, connected: bool)
+    {
+        if connected
+        {
+            self.handleInputEvent(InputEvent::ControllerConnected(slot));
+        }
+        else
+        {
+            self.handleInputEvent(InputEvent::ControllerDisconnected(slot));
+        }
+    }
+    ////////////////////////////////////////////////
+
+    // The backend-agnostic way to update the state.
+    // routeInputEvent ends up here, and so can anything
+    // else that produces InputEvents.
+    pub fn handleInputEvent(&mut self, event: InputEvent)
+    {
+        let event = if self.sticky.enabled { self.sticky.filter(event) } else { event };
+
+        match event
+        {
+            // The engine does its own repeating, see beginFrame
+            InputEvent::Key(_, KeyAction::Repeat, _) | InputEvent::Modifier(_, KeyAction::Repeat) if self.repeat.is_some() => {},
+            InputEvent::Key(key, action, mods) =>
+            {
+                self.key_mods = mods;
+                match action
+                {
+                    KeyAction::Press =>
+                    {
+                        self.standard_keys_mods[key as usize] = mods;
+                        self.standard_keys_repeat_count[key as usize] = 0;
+                        self.history.push(ComboInput::Key(key), self.frame_time);
+                    },
+                    KeyAction::Repeat =>    {self.standard_keys_repeated[key as usize] = true;},
+                    KeyAction::Release =>
+                    {
+                        self.standard_keys_repeat_count[key as usize] = 0;
+                        self.history.pushRelease(ComboInput::Key(key), self.frame_time);
+                    },
+                }
+                applyStandardKey(self, key, action);
+            },
+            InputEvent::Modifier(key, action) =>       applyModifierKey(self, key, action),
+            InputEvent::MouseButton(button, action, mods) =>
+            {
+                self.key_mods = mods;
+                match action
+                {
+                    KeyAction::Press =>
+                    {
+                        self.mouse_button_mods[button as usize] = mods;
+                        self.history.push(ComboInput::Mouse(button), self.frame_time);
+                    },
+                    KeyAction::Release =>   self.history.pushRelease(ComboInput::Mouse(button), self.frame_time),
+                    KeyAction::Repeat =>    {},
+                }
+                applyMouseButton(self, button, action);
+            },
+            InputEvent::CursorMoved(x, y) =>
+            {
+                if self.cursor_known
+                {
+                    self.cursor_dx += x - self.cursor_x;
+                    self.cursor_dy += y - self.cursor_y;
+                }
+                self.cursor_x = x;
+                self.cursor_y = y;
+                self.cursor_known = true;
+            },
+            InputEvent::Scroll(x, y) =>
+            {
+                self.scroll_x += x;
+                self.scroll_y += y;
+                self.scroll_dx += x;
+                self.scroll_dy += y;
+            },
+            InputEvent::CursorEntered(entered) =>
+            {
+                self.cursor_in_window = entered;
+                // Leaving and coming back somewhere else is not motion
+                self.cursor_known = false;
+            },
+            // Slots past MAX_CONTROLLERS can only come from a bad
+            // replay or injected event, there is nowhere to put them
+            InputEvent::ControllerConnected(slot) | InputEvent::ControllerDisconnected(slot) |
+            InputEvent::ControllerButton(slot, _, _) | InputEvent::ControllerAxis(slot, _, _)
+                if slot as usize >= self.controllers.len() => {},
+            InputEvent::ControllerConnected(slot) =>
+            {
+                self.controllers[slot as usize].reset();
+                self.controllers[slot as usize].connected = true;
+            },
+            InputEvent::ControllerDisconnected(slot) =>
+            {
+                // Whatever was held comes up with the pad, so a chord
+                // cannot count on a button from before it went away
+                for index in 0..KGB_COUNT
+                {
+                    if self.controllers[slot as usize].button_press[index]
+                    {
+                        if let Some(button) = KGB::fromIndex(index)
+                        {
+                            self.history.pushRelease(ComboInput::Pad(button), self.frame_time);
+                        }
+                    }
+                }
+                self.controllers[slot as usize].reset();
+            },
+            InputEvent::ControllerButton(slot, button, action) =>
+            {
+                let pad = &mut self.controllers[slot as usize];
+                match action
+                {
+                    KeyAction::Press =>
+                    {
+                        pad.button_press[button as usize] = true;
+                        pad.button_just_pressed[button as usize] = true;
+                        self.history.push(ComboInput::Pad(button), self.frame_time);
+                    },
+                    KeyAction::Release =>
+                    {
+                        pad.button_press[button as usize] = false;
+                        pad.button_just_released[button as usize] = true;
+                        self.history.pushRelease(ComboInput::Pad(button), self.frame_time);
+                    },
+                    KeyAction::Repeat => {},
+                }
+            },
+            InputEvent::ControllerAxis(slot, axis, value) =>
+            {
+                self.controllers[slot as usize].axes[axis as usize] = value;
+            },
+            // Typed text is TextInput's job
+            InputEvent::Char(_, _) => {},
+            InputEvent::Physical(scancode, action) =>
+            {
+                match action
+                {
+                    KeyAction::Press =>
+                    {
+                        if self.physical_keys_press.insert(scancode)
+                        {
+                            self.physical_keys_pressed_at.insert(scancode, self.frame_time);
+                        }
+                    },
+                    // The engine does its own repeating, see beginFrame
+                    KeyAction::Repeat =>
+                    {
+                        if self.repeat.is_none()
+                        {
+                            self.physical_keys_held.insert(scancode);
+                        }
+                    },
+                    KeyAction::Release =>
+                    {
+                        self.physical_keys_press.remove(&scancode);
+                        self.physical_keys_held.remove(&scancode);
+                        self.physical_keys_pressed_at.remove(&scancode);
+                    },
+                }
+            },
+            InputEvent::Focus(focused) =>
+            {
+                if !focused
+                {
+                    self.releaseAll();
+                }
+            },
+        }
+    }
+}
+
+fn heldFor(pressedAt: Option<Instant>, now: Instant, duration: Duration) -> bool
+{
+    return match pressedAt
+    {
+        Some(start) => now.saturating_duration_since(start) >= duration,
+        None => false,
+    };
 }
 
 macro_rules! key_used {
     (press => $key:expr, $state:expr) => {
         
+        if !$state.standard_keys_press[$key as usize]
+        {
+            $state.standard_keys_just_pressed[$key as usize] = true;
+            $state.standard_keys_pressed_at[$key as usize] = Some($state.frame_time);
+        }
         $state.standard_keys_press[$key as usize] = true;
 
     };
@@ -207,8 +1142,42 @@ macro_rules! key_used {
     };
     (release => $key:expr, $state:expr) => {
 
+        if $state.standard_keys_press[$key as usize] || $state.standard_keys_held[$key as usize]
+        {
+            $state.standard_keys_just_released[$key as usize] = true;
+        }
         $state.standard_keys_press[$key as usize] = false;
         $state.standard_keys_held[$key as usize] = false;
+        $state.standard_keys_pressed_at[$key as usize] = None;
+
+    };
+}
+
+macro_rules! modifier_used {
+    (press => $key:expr, $state:expr) => {
+        
+        if !$state.modifier_keys_press[$key as usize]
+        {
+            $state.modifier_keys_just_pressed[$key as usize] = true;
+            $state.modifier_keys_pressed_at[$key as usize] = Some($state.frame_time);
+        }
+        $state.modifier_keys_press[$key as usize] = true;
+
+    };
+    (hold => $key:expr, $state:expr) => {
+
+        $state.modifier_keys_held[$key as usize] = true;
+
+    };
+    (release => $key:expr, $state:expr) => {
+
+        if $state.modifier_keys_press[$key as usize] || $state.modifier_keys_held[$key as usize]
+        {
+            $state.modifier_keys_just_released[$key as usize] = true;
+        }
+        $state.modifier_keys_press[$key as usize] = false;
+        $state.modifier_keys_held[$key as usize] = false;
+        $state.modifier_keys_pressed_at[$key as usize] = None;
 
     };
 }
@@ -216,6 +1185,11 @@ macro_rules! key_used {
 macro_rules! mouse_button_used {
     (press => $key:expr, $state:expr) => {
         
+        if !$state.mouse_button_press[$key as usize]
+        {
+            $state.mouse_button_just_pressed[$key as usize] = true;
+            $state.mouse_button_pressed_at[$key as usize] = Some($state.frame_time);
+        }
         $state.mouse_button_press[$key as usize] = true;
 
     };
@@ -226,196 +1200,345 @@ macro_rules! mouse_button_used {
     };
     (release => $key:expr, $state:expr) => {
 
+        if $state.mouse_button_press[$key as usize] || $state.mouse_button_held[$key as usize]
+        {
+            $state.mouse_button_just_released[$key as usize] = true;
+        }
         $state.mouse_button_press[$key as usize] = false;
         $state.mouse_button_held[$key as usize] = false;
+        $state.mouse_button_pressed_at[$key as usize] = None;
 
     };
 }
 
-pub fn handleWindowEvent(window: &mut glfw::Window, event: glfw::WindowEvent, inState: &mut InputState) -> bool
+// Converts a GLFW key into its Kestrel key.
+// Keys the engine does not track yet come back as None.
+pub fn standardKeyFromGlfw(key: Key) -> Option<KSK>
 {
-
-    // How to reduce the size of this function?
-    // Maybe match event, then store the 4 
-    // parts of the Key Event, then convert Key to
-    // it's respective kestrel key?
-
-    match event
+    return match key
     {
-        /** Mouse Buttons **/
-        glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) =>   {mouse_button_used!(press => KMB::M1, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M1, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => {mouse_button_used!(release => KMB::M1, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _) =>   {mouse_button_used!(press => KMB::M2, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M2, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button2, Action::Release, _) => {mouse_button_used!(release => KMB::M2, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button3, Action::Press, _) =>   {mouse_button_used!(press => KMB::M3, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button3, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M3, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button3, Action::Release, _) => {mouse_button_used!(release => KMB::M3, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button4, Action::Press, _) =>   {mouse_button_used!(press => KMB::M4, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button4, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M4, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button4, Action::Release, _) => {mouse_button_used!(release => KMB::M4, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button5, Action::Press, _) =>   {mouse_button_used!(press => KMB::M5, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button5, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M5, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button5, Action::Release, _) => {mouse_button_used!(release => KMB::M5, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button6, Action::Press, _) =>   {mouse_button_used!(press => KMB::M6, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button6, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M6, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button6, Action::Release, _) => {mouse_button_used!(release => KMB::M6, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button7, Action::Press, _) =>   {mouse_button_used!(press => KMB::M7, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button7, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M7, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button7, Action::Release, _) => {mouse_button_used!(release => KMB::M7, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button8, Action::Press, _) =>   {mouse_button_used!(press => KMB::M8, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button8, Action::Repeat, _) =>  {mouse_button_used!(hold => KMB::M8, inState);},
-        glfw::WindowEvent::MouseButton(MouseButton::Button8, Action::Release, _) => {mouse_button_used!(release => KMB::M8, inState);},
-        /** Mouse Events (Scroll) **/
+        /** Letter Keys **/
+        Key::A =>        Some(KSK::A),
+        Key::B =>        Some(KSK::B),
+        Key::C =>        Some(KSK::C),
+        Key::D =>        Some(KSK::D),
+        Key::E =>        Some(KSK::E),
+        Key::F =>        Some(KSK::F),
+        Key::G =>        Some(KSK::G),
+        Key::H =>        Some(KSK::H),
+        Key::I =>        Some(KSK::I),
+        Key::J =>        Some(KSK::J),
+        Key::K =>        Some(KSK::K),
+        Key::L =>        Some(KSK::L),
+        Key::M =>        Some(KSK::M),
+        Key::N =>        Some(KSK::N),
+        Key::O =>        Some(KSK::O),
+        Key::P =>        Some(KSK::P),
+        Key::Q =>        Some(KSK::Q),
+        Key::R =>        Some(KSK::R),
+        Key::S =>        Some(KSK::S),
+        Key::T =>        Some(KSK::T),
+        Key::U =>        Some(KSK::U),
+        Key::V =>        Some(KSK::V),
+        Key::W =>        Some(KSK::W),
+        Key::X =>        Some(KSK::X),
+        Key::Y =>        Some(KSK::Y),
+        Key::Z =>        Some(KSK::Z),
+        /** Number Keys **/
+        Key::Num0 =>     Some(KSK::ZERO),
+        Key::Num1 =>     Some(KSK::ONE),
+        Key::Num2 =>     Some(KSK::TWO),
+        Key::Num3 =>     Some(KSK::THREE),
+        Key::Num4 =>     Some(KSK::FOUR),
+        Key::Num5 =>     Some(KSK::FIVE),
+        Key::Num6 =>     Some(KSK::SIX),
+        Key::Num7 =>     Some(KSK::SEVEN),
+        Key::Num8 =>     Some(KSK::EIGHT),
+        Key::Num9 =>     Some(KSK::NINE),
+        /** Keypad Number Keys **/
+        Key::Kp0 =>      Some(KSK::NUMPAD_ZERO),
+        Key::Kp1 =>      Some(KSK::NUMPAD_ONE),
+        Key::Kp2 =>      Some(KSK::NUMPAD_TWO),
+        Key::Kp3 =>      Some(KSK::NUMPAD_THREE),
+        Key::Kp4 =>      Some(KSK::NUMPAD_FOUR),
+        Key::Kp5 =>      Some(KSK::NUMPAD_FIVE),
+        Key::Kp6 =>      Some(KSK::NUMPAD_SIX),
+        Key::Kp7 =>      Some(KSK::NUMPAD_SEVEN),
+        Key::Kp8 =>      Some(KSK::NUMPAD_EIGHT),
+        Key::Kp9 =>      Some(KSK::NUMPAD_NINE),
+        /** Number Pad Keys **/
+        Key::KpDecimal =>   Some(KSK::NUMPAD_DECIMAL),
+        Key::KpDivide =>    Some(KSK::NUMPAD_DIVIDE),
+        Key::KpMultiply =>  Some(KSK::NUMPAD_MULTIPLY),
+        Key::KpSubtract =>  Some(KSK::NUMPAD_SUBTRACT),
+        Key::KpAdd =>       Some(KSK::NUMPAD_ADD),
+        Key::KpEnter =>     Some(KSK::NUMPAD_ENTER),
+        Key::KpEqual =>     Some(KSK::NUMPAD_EQUAL),
+        /** Function Keys **/
+        Key::F1 =>          Some(KSK::F1),
+        Key::F2 =>          Some(KSK::F2),
+        Key::F3 =>          Some(KSK::F3),
+        Key::F4 =>          Some(KSK::F4),
+        Key::F5 =>          Some(KSK::F5),
+        Key::F6 =>          Some(KSK::F6),
+        Key::F7 =>          Some(KSK::F7),
+        Key::F8 =>          Some(KSK::F8),
+        Key::F9 =>          Some(KSK::F9),
+        Key::F10 =>         Some(KSK::F10),
+        Key::F11 =>         Some(KSK::F11),
+        Key::F12 =>         Some(KSK::F12),
+        Key::F13 =>         Some(KSK::F13),
+        Key::F14 =>         Some(KSK::F14),
+        Key::F15 =>         Some(KSK::F15),
+        Key::F16 =>         Some(KSK::F16),
+        Key::F17 =>         Some(KSK::F17),
+        Key::F18 =>         Some(KSK::F18),
+        Key::F19 =>         Some(KSK::F19),
+        Key::F20 =>         Some(KSK::F20),
+        Key::F21 =>         Some(KSK::F21),
+        Key::F22 =>         Some(KSK::F22),
+        Key::F23 =>         Some(KSK::F23),
+        Key::F24 =>         Some(KSK::F24),
+        Key::F25 =>         Some(KSK::F25),
+        /** Arrow Keys **/
+        Key::Up =>          Some(KSK::UP),
+        Key::Down =>        Some(KSK::DOWN),
+        Key::Left =>        Some(KSK::LEFT),
+        Key::Right =>       Some(KSK::RIGHT),
+        /** Editing Keys **/
+        Key::Escape =>      Some(KSK::ESCAPE),
+        Key::Enter =>       Some(KSK::ENTER),
+        Key::Tab =>         Some(KSK::TAB),
+        Key::Backspace =>   Some(KSK::BACKSPACE),
+        Key::Insert =>      Some(KSK::INSERT),
+        Key::Delete =>      Some(KSK::DELETE),
+        Key::Home =>        Some(KSK::HOME),
+        Key::End =>         Some(KSK::END),
+        Key::PageUp =>      Some(KSK::PAGE_UP),
+        Key::PageDown =>    Some(KSK::PAGE_DOWN),
+        /** Punctuation Keys **/
+        Key::Space =>       Some(KSK::SPACE),
+        Key::Apostrophe =>  Some(KSK::APOSTROPHE),
+        Key::Comma =>       Some(KSK::COMMA),
+        Key::Minus =>       Some(KSK::MINUS),
+        Key::Period =>      Some(KSK::PERIOD),
+        Key::Slash =>       Some(KSK::SLASH),
+        Key::Semicolon =>   Some(KSK::SEMICOLON),
+        Key::Equal =>       Some(KSK::EQUAL),
+        Key::LeftBracket => Some(KSK::LEFT_BRACKET),
+        Key::Backslash =>   Some(KSK::BACKSLASH),
+        Key::RightBracket => Some(KSK::RIGHT_BRACKET),
+        Key::GraveAccent => Some(KSK::GRAVE_ACCENT),
+        Key::World1 =>      Some(KSK::WORLD_ONE),
+        Key::World2 =>      Some(KSK::WORLD_TWO),
+        /** Lock Keys **/
+        Key::CapsLock =>    Some(KSK::CAPS_LOCK),
+        Key::ScrollLock =>  Some(KSK::SCROLL_LOCK),
+        Key::NumLock =>     Some(KSK::NUM_LOCK),
+        /** Misc Keys **/
+        Key::PrintScreen => Some(KSK::PRINT_SCREEN),
+        Key::Pause =>       Some(KSK::PAUSE),
+        Key::Menu =>        Some(KSK::MENU),
+        _ =>            None,
+    };
+}
 
+// The other way round, for asking GLFW about a key
+pub fn standardKeyToGlfw(key: KSK) -> Key
+{
+    return match key
+    {
         /** Letter Keys **/
-        glfw::WindowEvent::Key(Key::A, _, Action::Press, _) =>      {key_used!(press => KSK::A, inState);},
-        glfw::WindowEvent::Key(Key::A, _, Action::Repeat, _) =>     {key_used!(hold => KSK::A, inState);},
-        glfw::WindowEvent::Key(Key::A, _, Action::Release, _) =>    {key_used!(release => KSK::A, inState);},
-        glfw::WindowEvent::Key(Key::B, _, Action::Press, _) =>      {key_used!(press => KSK::B, inState);},
-        glfw::WindowEvent::Key(Key::B, _, Action::Repeat, _) =>     {key_used!(hold => KSK::B, inState);},
-        glfw::WindowEvent::Key(Key::B, _, Action::Release, _) =>    {key_used!(release => KSK::B, inState);},
-        glfw::WindowEvent::Key(Key::C, _, Action::Press, _) =>      {key_used!(press => KSK::C, inState);},
-        glfw::WindowEvent::Key(Key::C, _, Action::Repeat, _) =>     {key_used!(hold => KSK::C, inState);},
-        glfw::WindowEvent::Key(Key::C, _, Action::Release, _) =>    {key_used!(release => KSK::C, inState);},
-        glfw::WindowEvent::Key(Key::D, _, Action::Press, _) =>      {key_used!(press => KSK::D, inState);},
-        glfw::WindowEvent::Key(Key::D, _, Action::Repeat, _) =>     {key_used!(hold => KSK::D, inState);},
-        glfw::WindowEvent::Key(Key::D, _, Action::Release, _) =>    {key_used!(release => KSK::D, inState);},
-        glfw::WindowEvent::Key(Key::E, _, Action::Press, _) =>      {key_used!(press => KSK::E, inState);},
-        glfw::WindowEvent::Key(Key::E, _, Action::Repeat, _) =>     {key_used!(hold => KSK::E, inState);},
-        glfw::WindowEvent::Key(Key::E, _, Action::Release, _) =>    {key_used!(release => KSK::E, inState);},
-        glfw::WindowEvent::Key(Key::F, _, Action::Press, _) =>      {key_used!(press => KSK::F, inState);},
-        glfw::WindowEvent::Key(Key::F, _, Action::Repeat, _) =>     {key_used!(hold => KSK::F, inState);},
-        glfw::WindowEvent::Key(Key::F, _, Action::Release, _) =>    {key_used!(release => KSK::F, inState);},
-        glfw::WindowEvent::Key(Key::G, _, Action::Press, _) =>      {key_used!(press => KSK::G, inState);},
-        glfw::WindowEvent::Key(Key::G, _, Action::Repeat, _) =>     {key_used!(hold => KSK::G, inState);},
-        glfw::WindowEvent::Key(Key::G, _, Action::Release, _) =>    {key_used!(release => KSK::G, inState);},
-        glfw::WindowEvent::Key(Key::H, _, Action::Press, _) =>      {key_used!(press => KSK::H, inState);},
-        glfw::WindowEvent::Key(Key::H, _, Action::Repeat, _) =>     {key_used!(hold => KSK::H, inState);},
-        glfw::WindowEvent::Key(Key::H, _, Action::Release, _) =>    {key_used!(release => KSK::H, inState);},
-        glfw::WindowEvent::Key(Key::I, _, Action::Press, _) =>      {key_used!(press => KSK::I, inState);},
-        glfw::WindowEvent::Key(Key::I, _, Action::Repeat, _) =>     {key_used!(hold => KSK::I, inState);},
-        glfw::WindowEvent::Key(Key::I, _, Action::Release, _) =>    {key_used!(release => KSK::I, inState);},
-        glfw::WindowEvent::Key(Key::J, _, Action::Press, _) =>      {key_used!(press => KSK::J, inState);},
-        glfw::WindowEvent::Key(Key::J, _, Action::Repeat, _) =>     {key_used!(hold => KSK::J, inState);},
-        glfw::WindowEvent::Key(Key::J, _, Action::Release, _) =>    {key_used!(release => KSK::J, inState);},
-        glfw::WindowEvent::Key(Key::K, _, Action::Press, _) =>      {key_used!(press => KSK::K, inState);},
-        glfw::WindowEvent::Key(Key::K, _, Action::Repeat, _) =>     {key_used!(hold => KSK::K, inState);},
-        glfw::WindowEvent::Key(Key::K, _, Action::Release, _) =>    {key_used!(release => KSK::K, inState);},
-        glfw::WindowEvent::Key(Key::L, _, Action::Press, _) =>      {key_used!(press => KSK::L, inState);},
-        glfw::WindowEvent::Key(Key::L, _, Action::Repeat, _) =>     {key_used!(hold => KSK::L, inState);},
-        glfw::WindowEvent::Key(Key::L, _, Action::Release, _) =>    {key_used!(release => KSK::L, inState);},
-        glfw::WindowEvent::Key(Key::M, _, Action::Press, _) =>      {key_used!(press => KSK::M, inState);},
-        glfw::WindowEvent::Key(Key::M, _, Action::Repeat, _) =>     {key_used!(hold => KSK::M, inState);},
-        glfw::WindowEvent::Key(Key::M, _, Action::Release, _) =>    {key_used!(release => KSK::M, inState);},
-        glfw::WindowEvent::Key(Key::N, _, Action::Press, _) =>      {key_used!(press => KSK::N, inState);},
-        glfw::WindowEvent::Key(Key::N, _, Action::Repeat, _) =>     {key_used!(hold => KSK::N, inState);},
-        glfw::WindowEvent::Key(Key::N, _, Action::Release, _) =>    {key_used!(release => KSK::N, inState);},
-        glfw::WindowEvent::Key(Key::O, _, Action::Press, _) =>      {key_used!(press => KSK::O, inState);},
-        glfw::WindowEvent::Key(Key::O, _, Action::Repeat, _) =>     {key_used!(hold => KSK::O, inState);},
-        glfw::WindowEvent::Key(Key::O, _, Action::Release, _) =>    {key_used!(release => KSK::O, inState);},
-        glfw::WindowEvent::Key(Key::P, _, Action::Press, _) =>      {key_used!(press => KSK::P, inState);},
-        glfw::WindowEvent::Key(Key::P, _, Action::Repeat, _) =>     {key_used!(hold => KSK::P, inState);},
-        glfw::WindowEvent::Key(Key::P, _, Action::Release, _) =>    {key_used!(release => KSK::P, inState);},
-        glfw::WindowEvent::Key(Key::Q, _, Action::Press, _) =>      {key_used!(press => KSK::Q, inState);},
-        glfw::WindowEvent::Key(Key::Q, _, Action::Repeat, _) =>     {key_used!(hold => KSK::Q, inState);},
-        glfw::WindowEvent::Key(Key::Q, _, Action::Release, _) =>    {key_used!(release => KSK::Q, inState);},
-        glfw::WindowEvent::Key(Key::R, _, Action::Press, _) =>      {key_used!(press => KSK::R, inState);},
-        glfw::WindowEvent::Key(Key::R, _, Action::Repeat, _) =>     {key_used!(hold => KSK::R, inState);},
-        glfw::WindowEvent::Key(Key::R, _, Action::Release, _) =>    {key_used!(release => KSK::R, inState);},
-        glfw::WindowEvent::Key(Key::S, _, Action::Press, _) =>      {key_used!(press => KSK::S, inState);},
-        glfw::WindowEvent::Key(Key::S, _, Action::Repeat, _) =>     {key_used!(hold => KSK::S, inState);},
-        glfw::WindowEvent::Key(Key::S, _, Action::Release, _) =>    {key_used!(release => KSK::S, inState);},
-        glfw::WindowEvent::Key(Key::T, _, Action::Press, _) =>      {key_used!(press => KSK::T, inState);},
-        glfw::WindowEvent::Key(Key::T, _, Action::Repeat, _) =>     {key_used!(hold => KSK::T, inState);},
-        glfw::WindowEvent::Key(Key::T, _, Action::Release, _) =>    {key_used!(release => KSK::T, inState);},
-        glfw::WindowEvent::Key(Key::U, _, Action::Press, _) =>      {key_used!(press => KSK::U, inState);},
-        glfw::WindowEvent::Key(Key::U, _, Action::Repeat, _) =>     {key_used!(hold => KSK::U, inState);},
-        glfw::WindowEvent::Key(Key::U, _, Action::Release, _) =>    {key_used!(release => KSK::U, inState);},
-        glfw::WindowEvent::Key(Key::V, _, Action::Press, _) =>      {key_used!(press => KSK::V, inState);},
-        glfw::WindowEvent::Key(Key::V, _, Action::Repeat, _) =>     {key_used!(hold => KSK::V, inState);},
-        glfw::WindowEvent::Key(Key::V, _, Action::Release, _) =>    {key_used!(release => KSK::V, inState);},
-        glfw::WindowEvent::Key(Key::W, _, Action::Press, _) =>      {key_used!(press => KSK::W, inState);},
-        glfw::WindowEvent::Key(Key::W, _, Action::Repeat, _) =>     {key_used!(hold => KSK::W, inState);},
-        glfw::WindowEvent::Key(Key::W, _, Action::Release, _) =>    {key_used!(release => KSK::W, inState);},
-        glfw::WindowEvent::Key(Key::X, _, Action::Press, _) =>      {key_used!(press => KSK::X, inState);},
-        glfw::WindowEvent::Key(Key::X, _, Action::Repeat, _) =>     {key_used!(hold => KSK::X, inState);},
-        glfw::WindowEvent::Key(Key::X, _, Action::Release, _) =>    {key_used!(release => KSK::X, inState);},
-        glfw::WindowEvent::Key(Key::Y, _, Action::Press, _) =>      {key_used!(press => KSK::Y, inState);},
-        glfw::WindowEvent::Key(Key::Y, _, Action::Repeat, _) =>     {key_used!(hold => KSK::Y, inState);},
-        glfw::WindowEvent::Key(Key::Y, _, Action::Release, _) =>    {key_used!(release => KSK::Y, inState);},
-        glfw::WindowEvent::Key(Key::Z, _, Action::Press, _) =>      {key_used!(press => KSK::Z, inState);},
-        glfw::WindowEvent::Key(Key::Z, _, Action::Repeat, _) =>     {key_used!(hold => KSK::Z, inState);},
-        glfw::WindowEvent::Key(Key::Z, _, Action::Release, _) =>    {key_used!(release => KSK::Z, inState);},
+        KSK::A =>               Key::A,
+        KSK::B =>               Key::B,
+        KSK::C =>               Key::C,
+        KSK::D =>               Key::D,
+        KSK::E =>               Key::E,
+        KSK::F =>               Key::F,
+        KSK::G =>               Key::G,
+        KSK::H =>               Key::H,
+        KSK::I =>               Key::I,
+        KSK::J =>               Key::J,
+        KSK::K =>               Key::K,
+        KSK::L =>               Key::L,
+        KSK::M =>               Key::M,
+        KSK::N =>               Key::N,
+        KSK::O =>               Key::O,
+        KSK::P =>               Key::P,
+        KSK::Q =>               Key::Q,
+        KSK::R =>               Key::R,
+        KSK::S =>               Key::S,
+        KSK::T =>               Key::T,
+        KSK::U =>               Key::U,
+        KSK::V =>               Key::V,
+        KSK::W =>               Key::W,
+        KSK::X =>               Key::X,
+        KSK::Y =>               Key::Y,
+        KSK::Z =>               Key::Z,
         /** Number Keys **/
-        glfw::WindowEvent::Key(Key::Num0, _, Action::Press, _) =>      {key_used!(press => KSK::ZERO, inState);},
-        glfw::WindowEvent::Key(Key::Num0, _, Action::Repeat, _) =>     {key_used!(hold => KSK::ZERO, inState);},
-        glfw::WindowEvent::Key(Key::Num0, _, Action::Release, _) =>    {key_used!(release => KSK::ZERO, inState);},
-        glfw::WindowEvent::Key(Key::Num1, _, Action::Press, _) =>      {key_used!(press => KSK::ONE, inState);},
-        glfw::WindowEvent::Key(Key::Num1, _, Action::Repeat, _) =>     {key_used!(hold => KSK::ONE, inState);},
-        glfw::WindowEvent::Key(Key::Num1, _, Action::Release, _) =>    {key_used!(release => KSK::ONE, inState);},
-        glfw::WindowEvent::Key(Key::Num2, _, Action::Press, _) =>      {key_used!(press => KSK::TWO, inState);},
-        glfw::WindowEvent::Key(Key::Num2, _, Action::Repeat, _) =>     {key_used!(hold => KSK::TWO, inState);},
-        glfw::WindowEvent::Key(Key::Num2, _, Action::Release, _) =>    {key_used!(release => KSK::TWO, inState);},
-        glfw::WindowEvent::Key(Key::Num3, _, Action::Press, _) =>      {key_used!(press => KSK::THREE, inState);},
-        glfw::WindowEvent::Key(Key::Num3, _, Action::Repeat, _) =>     {key_used!(hold => KSK::THREE, inState);},
-        glfw::WindowEvent::Key(Key::Num3, _, Action::Release, _) =>    {key_used!(release => KSK::THREE, inState);},
-        glfw::WindowEvent::Key(Key::Num4, _, Action::Press, _) =>      {key_used!(press => KSK::FOUR, inState);},
-        glfw::WindowEvent::Key(Key::Num4, _, Action::Repeat, _) =>     {key_used!(hold => KSK::FOUR, inState);},
-        glfw::WindowEvent::Key(Key::Num4, _, Action::Release, _) =>    {key_used!(release => KSK::FOUR, inState);},
-        glfw::WindowEvent::Key(Key::Num5, _, Action::Press, _) =>      {key_used!(press => KSK::FIVE, inState);},
-        glfw::WindowEvent::Key(Key::Num5, _, Action::Repeat, _) =>     {key_used!(hold => KSK::FIVE, inState);},
-        glfw::WindowEvent::Key(Key::Num5, _, Action::Release, _) =>    {key_used!(release => KSK::FIVE, inState);},
-        glfw::WindowEvent::Key(Key::Num6, _, Action::Press, _) =>      {key_used!(press => KSK::SIX, inState);},
-        glfw::WindowEvent::Key(Key::Num6, _, Action::Repeat, _) =>     {key_used!(hold => KSK::SIX, inState);},
-        glfw::WindowEvent::Key(Key::Num6, _, Action::Release, _) =>    {key_used!(release => KSK::SIX, inState);},
-        glfw::WindowEvent::Key(Key::Num7, _, Action::Press, _) =>      {key_used!(press => KSK::SEVEN, inState);},
-        glfw::WindowEvent::Key(Key::Num7, _, Action::Repeat, _) =>     {key_used!(hold => KSK::SEVEN, inState);},
-        glfw::WindowEvent::Key(Key::Num7, _, Action::Release, _) =>    {key_used!(release => KSK::SEVEN, inState);},
-        glfw::WindowEvent::Key(Key::Num8, _, Action::Press, _) =>      {key_used!(press => KSK::EIGHT, inState);},
-        glfw::WindowEvent::Key(Key::Num8, _, Action::Repeat, _) =>     {key_used!(hold => KSK::EIGHT, inState);},
-        glfw::WindowEvent::Key(Key::Num8, _, Action::Release, _) =>    {key_used!(release => KSK::EIGHT, inState);},
-        glfw::WindowEvent::Key(Key::Num9, _, Action::Press, _) =>      {key_used!(press => KSK::NINE, inState);},
-        glfw::WindowEvent::Key(Key::Num9, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NINE, inState);},
-        glfw::WindowEvent::Key(Key::Num9, _, Action::Release, _) =>    {key_used!(release => KSK::NINE, inState);},
+        KSK::ZERO =>            Key::Num0,
+        KSK::ONE =>             Key::Num1,
+        KSK::TWO =>             Key::Num2,
+        KSK::THREE =>           Key::Num3,
+        KSK::FOUR =>            Key::Num4,
+        KSK::FIVE =>            Key::Num5,
+        KSK::SIX =>             Key::Num6,
+        KSK::SEVEN =>           Key::Num7,
+        KSK::EIGHT =>           Key::Num8,
+        KSK::NINE =>            Key::Num9,
         /** Keypad Number Keys **/
-        glfw::WindowEvent::Key(Key::Kp0, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_ZERO, inState);},
-        glfw::WindowEvent::Key(Key::Kp0, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_ZERO, inState);},
-        glfw::WindowEvent::Key(Key::Kp0, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_ZERO, inState);},
-        glfw::WindowEvent::Key(Key::Kp1, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_ONE, inState);},
-        glfw::WindowEvent::Key(Key::Kp1, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_ONE, inState);},
-        glfw::WindowEvent::Key(Key::Kp1, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_ONE, inState);},
-        glfw::WindowEvent::Key(Key::Kp2, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_TWO, inState);},
-        glfw::WindowEvent::Key(Key::Kp2, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_TWO, inState);},
-        glfw::WindowEvent::Key(Key::Kp2, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_TWO, inState);},
-        glfw::WindowEvent::Key(Key::Kp3, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_THREE, inState);},
-        glfw::WindowEvent::Key(Key::Kp3, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_THREE, inState);},
-        glfw::WindowEvent::Key(Key::Kp3, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_THREE, inState);},
-        glfw::WindowEvent::Key(Key::Kp4, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_FOUR, inState);},
-        glfw::WindowEvent::Key(Key::Kp4, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_FOUR, inState);},
-        glfw::WindowEvent::Key(Key::Kp4, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_FOUR, inState);},
-        glfw::WindowEvent::Key(Key::Kp5, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_FIVE, inState);},
-        glfw::WindowEvent::Key(Key::Kp5, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_FIVE, inState);},
-        glfw::WindowEvent::Key(Key::Kp5, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_FIVE, inState);},
-        glfw::WindowEvent::Key(Key::Kp6, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_SIX, inState);},
-        glfw::WindowEvent::Key(Key::Kp6, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_SIX, inState);},
-        glfw::WindowEvent::Key(Key::Kp6, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_SIX, inState);},
-        glfw::WindowEvent::Key(Key::Kp7, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_SEVEN, inState);},
-        glfw::WindowEvent::Key(Key::Kp7, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_SEVEN, inState);},
-        glfw::WindowEvent::Key(Key::Kp7, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_SEVEN, inState);},
-        glfw::WindowEvent::Key(Key::Kp8, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_EIGHT, inState);},
-        glfw::WindowEvent::Key(Key::Kp8, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_EIGHT, inState);},
-        glfw::WindowEvent::Key(Key::Kp8, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_EIGHT, inState);},
-        glfw::WindowEvent::Key(Key::Kp9, _, Action::Press, _) =>      {key_used!(press => KSK::NUMPAD_NINE, inState);},
-        glfw::WindowEvent::Key(Key::Kp9, _, Action::Repeat, _) =>     {key_used!(hold => KSK::NUMPAD_NINE, inState);},
-        glfw::WindowEvent::Key(Key::Kp9, _, Action::Release, _) =>    {key_used!(release => KSK::NUMPAD_NINE, inState);},
+        KSK::NUMPAD_ZERO =>     Key::Kp0,
+        KSK::NUMPAD_ONE =>      Key::Kp1,
+        KSK::NUMPAD_TWO =>      Key::Kp2,
+        KSK::NUMPAD_THREE =>    Key::Kp3,
+        KSK::NUMPAD_FOUR =>     Key::Kp4,
+        KSK::NUMPAD_FIVE =>     Key::Kp5,
+        KSK::NUMPAD_SIX =>      Key::Kp6,
+        KSK::NUMPAD_SEVEN =>    Key::Kp7,
+        KSK::NUMPAD_EIGHT =>    Key::Kp8,
+        KSK::NUMPAD_NINE =>     Key::Kp9,
         /** Number Pad Keys **/
-        
-        
+        KSK::NUMPAD_DECIMAL =>  Key::KpDecimal,
+        KSK::NUMPAD_DIVIDE =>   Key::KpDivide,
+        KSK::NUMPAD_MULTIPLY => Key::KpMultiply,
+        KSK::NUMPAD_SUBTRACT => Key::KpSubtract,
+        KSK::NUMPAD_ADD =>      Key::KpAdd,
+        KSK::NUMPAD_ENTER =>    Key::KpEnter,
+        KSK::NUMPAD_EQUAL =>    Key::KpEqual,
+        /** Function Keys **/
+        KSK::F1 =>              Key::F1,
+        KSK::F2 =>              Key::F2,
+        KSK::F3 =>              Key::F3,
+        KSK::F4 =>              Key::F4,
+        KSK::F5 =>              Key::F5,
+        KSK::F6 =>              Key::F6,
+        KSK::F7 =>              Key::F7,
+        KSK::F8 =>              Key::F8,
+        KSK::F9 =>              Key::F9,
+        KSK::F10 =>             Key::F10,
+        KSK::F11 =>             Key::F11,
+        KSK::F12 =>             Key::F12,
+        KSK::F13 =>             Key::F13,
+        KSK::F14 =>             Key::F14,
+        KSK::F15 =>             Key::F15,
+        KSK::F16 =>             Key::F16,
+        KSK::F17 =>             Key::F17,
+        KSK::F18 =>             Key::F18,
+        KSK::F19 =>             Key::F19,
+        KSK::F20 =>             Key::F20,
+        KSK::F21 =>             Key::F21,
+        KSK::F22 =>             Key::F22,
+        KSK::F23 =>             Key::F23,
+        KSK::F24 =>             Key::F24,
+        KSK::F25 =>             Key::F25,
         /** Arrow Keys **/
-        
-        
+        KSK::UP =>              Key::Up,
+        KSK::DOWN =>            Key::Down,
+        KSK::LEFT =>            Key::Left,
+        KSK::RIGHT =>           Key::Right,
+        /** Editing Keys **/
+        KSK::ESCAPE =>          Key::Escape,
+        KSK::ENTER =>           Key::Enter,
+        KSK::TAB =>             Key::Tab,
+        KSK::BACKSPACE =>       Key::Backspace,
+        KSK::INSERT =>          Key::Insert,
+        KSK::DELETE =>          Key::Delete,
+        KSK::HOME =>            Key::Home,
+        KSK::END =>             Key::End,
+        KSK::PAGE_UP =>         Key::PageUp,
+        KSK::PAGE_DOWN =>       Key::PageDown,
+        /** Punctuation Keys **/
+        KSK::SPACE =>           Key::Space,
+        KSK::APOSTROPHE =>      Key::Apostrophe,
+        KSK::COMMA =>           Key::Comma,
+        KSK::MINUS =>           Key::Minus,
+        KSK::PERIOD =>          Key::Period,
+        KSK::SLASH =>           Key::Slash,
+        KSK::SEMICOLON =>       Key::Semicolon,
+        KSK::EQUAL =>           Key::Equal,
+        KSK::LEFT_BRACKET =>    Key::LeftBracket,
+        KSK::BACKSLASH =>       Key::Backslash,
+        KSK::RIGHT_BRACKET =>   Key::RightBracket,
+        KSK::GRAVE_ACCENT =>    Key::GraveAccent,
+        KSK::WORLD_ONE =>       Key::World1,
+        KSK::WORLD_TWO =>       Key::World2,
+        /** Lock Keys **/
+        KSK::CAPS_LOCK =>       Key::CapsLock,
+        KSK::SCROLL_LOCK =>     Key::ScrollLock,
+        KSK::NUM_LOCK =>        Key::NumLock,
+        /** Misc Keys **/
+        KSK::PRINT_SCREEN =>    Key::PrintScreen,
+        KSK::PAUSE =>           Key::Pause,
+        KSK::MENU =>            Key::Menu,
+    };
+}
+
+pub fn modifierKeyFromGlfw(key: Key) -> Option<KMK>
+{
+    return match key
+    {
+        Key::LeftShift =>       Some(KMK::LShift),
+        Key::RightShift =>      Some(KMK::RShift),
+        Key::LeftControl =>     Some(KMK::LControl),
+        Key::RightControl =>    Some(KMK::RControl),
+        Key::LeftAlt =>         Some(KMK::LAlt),
+        Key::RightAlt =>        Some(KMK::RAlt),
+        Key::LeftSuper =>       Some(KMK::LSuper),
+        Key::RightSuper =>      Some(KMK::RSuper),
+        _ =>                    None,
+    };
+}
+
+pub fn mouseButtonFromGlfw(button: MouseButton) -> Option<KMB>
+{
+    return match button
+    {
+        MouseButton::Button1 =>   Some(KMB::M1),
+        MouseButton::Button2 =>   Some(KMB::M2),
+        MouseButton::Button3 =>   Some(KMB::M3),
+        MouseButton::Button4 =>   Some(KMB::M4),
+        MouseButton::Button5 =>   Some(KMB::M5),
+        MouseButton::Button6 =>   Some(KMB::M6),
+        MouseButton::Button7 =>   Some(KMB::M7),
+        MouseButton::Button8 =>   Some(KMB::M8),
+    };
+}
+
+pub fn applyStandardKey(inState: &mut InputState, key: KSK, action: KeyAction)
+{
+    match action
+    {
+        KeyAction::Press =>    {key_used!(press => key, inState);},
+        KeyAction::Repeat =>   {key_used!(hold => key, inState);},
+        KeyAction::Release =>  {key_used!(release => key, inState);},
+    }
+}
+
+pub fn applyModifierKey(inState: &mut InputState, key: KMK, action: KeyAction)
+{
+    match action
+    {
+        KeyAction::Press =>    {modifier_used!(press => key, inState);},
+        KeyAction::Repeat =>   {modifier_used!(hold => key, inState);},
+        KeyAction::Release =>  {modifier_used!(release => key, inState);},
+    }
+}
+
+pub fn applyMouseButton(inState: &mut InputState, button: KMB, action: KeyAction)
+{
+    match action
+    {
+        KeyAction::Press =>    {mouse_button_used!(press => button, inState);},
+        KeyAction::Repeat =>   {mouse_button_used!(hold => button, inState);},
+        KeyAction::Release =>  {mouse_button_used!(release => button, inState);},
+    }
+}
+
+// The window side of an event, the input side goes through routeInputEvent
+pub fn handleWindowEvent(window: &mut glfw::Window, event: glfw::WindowEvent) -> bool
+{
+    match event
+    {
         /** Misc Keys **/
         glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) =>
         {
@@ -435,7 +1558,7 @@ fn pressTest1()
 {
     let mut inpState = InputState::new();
 
-    key_used!(press => KSK::Z, inpState);
+    inpState.injectKey(KSK::Z, KeyAction::Press);
 
     assert_eq!(inpState.isStandardPressed(KSK::Z as u64), true);
 
@@ -446,11 +1569,11 @@ fn pressTest2()
 {
     let mut inpState = InputState::new();
 
-    key_used!(press => KSK::Z, inpState);
+    inpState.injectKey(KSK::Z, KeyAction::Press);
 
     assert_eq!(inpState.isStandardPressed(KSK::Z as u64), true);
 
-    key_used!(release => KSK::Z, inpState);
+    inpState.injectKey(KSK::Z, KeyAction::Release);
 
     assert_eq!(inpState.isStandardPressed(KSK::Z as u64), false);
 
@@ -461,7 +1584,7 @@ fn heldTest1()
 {
     let mut inpState = InputState::new();
 
-    key_used!(hold => KSK::Z, inpState);
+    inpState.injectKey(KSK::Z, KeyAction::Repeat);
 
     assert_eq!(inpState.isStandardHeld(KSK::Z as u64), true);
 
@@ -472,11 +1595,11 @@ fn heldTest2()
 {
     let mut inpState = InputState::new();
 
-    key_used!(hold => KSK::Z, inpState);
+    inpState.injectKey(KSK::Z, KeyAction::Repeat);
     
     assert_eq!(inpState.isStandardHeld(KSK::Z as u64), true);
 
-    key_used!(release => KSK::Z, inpState);
+    inpState.injectKey(KSK::Z, KeyAction::Release);
 
     assert_eq!(inpState.isStandardHeld(KSK::Z as u64), false);
 }
@@ -486,11 +1609,11 @@ fn mousePressTest()
 {
     let mut inpState = InputState::new();
 
-    mouse_button_used!(press => KMB::M1, inpState);
+    inpState.injectMouseButton(KMB::M1, KeyAction::Press);
     
     assert_eq!(inpState.isMouseButtonPressed(KMB::M1 as u16), true);
     
-    mouse_button_used!(release => KMB::M1, inpState);
+    inpState.injectMouseButton(KMB::M1, KeyAction::Release);
 
     assert_eq!(inpState.isMouseButtonPressed(KMB::M1 as u16), false);
 
@@ -501,13 +1624,267 @@ fn mouseHeldTest()
 {
     let mut inpState = InputState::new();
 
-    mouse_button_used!(hold => KMB::M1, inpState);
+    inpState.injectMouseButton(KMB::M1, KeyAction::Repeat);
 
     assert_eq!(inpState.isMouseButtonHeld(KMB::M1 as u16), true);
 
-    mouse_button_used!(release => KMB::M1, inpState);
+    inpState.injectMouseButton(KMB::M1, KeyAction::Release);
 
     assert_eq!(inpState.isMouseButtonHeld(KMB::M1 as u16), false);
 }
 
-/*************************************/
\ No newline at end of file
+#[test]
+fn keyNameTest()
+{
+    assert_eq!(KSK::W.name(), "W");
+    assert_eq!(KSK::fromName("NUMPAD_NINE"), Some(KSK::NUMPAD_NINE));
+    assert_eq!(KSK::fromName("not a key"), None);
+    assert_eq!(KMK::fromName(KMK::RControl.name()), Some(KMK::RControl));
+    assert_eq!(KMB::fromName(KMB::M10.name()), Some(KMB::M10));
+}
+
+#[test]
+fn inputEventTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.handleInputEvent(InputEvent::Key(KSK::Q, KeyAction::Press, KeyMods::NONE));
+    inpState.handleInputEvent(InputEvent::MouseButton(KMB::M3, KeyAction::Repeat, KeyMods::NONE));
+
+    assert_eq!(inpState.isStandardPressed(KSK::Q as u64), true);
+    assert_eq!(inpState.isMouseButtonHeld(KMB::M3 as u16), true);
+
+    inpState.handleInputEvent(InputEvent::Key(KSK::Q, KeyAction::Release, KeyMods::NONE));
+
+    assert_eq!(inpState.isStandardPressed(KSK::Q as u64), false);
+}
+
+#[test]
+fn justPressedTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.beginFrame(Instant::now());
+    inpState.injectKey(KSK::E, KeyAction::Press);
+
+    assert_eq!(inpState.isStandardJustPressed(KSK::E as u64), true);
+
+    inpState.endFrame();
+
+    assert_eq!(inpState.isStandardJustPressed(KSK::E as u64), false);
+    assert_eq!(inpState.isStandardPressed(KSK::E as u64), true);
+
+    inpState.injectKey(KSK::E, KeyAction::Release);
+
+    assert_eq!(inpState.isStandardJustReleased(KSK::E as u64), true);
+
+    inpState.endFrame();
+
+    assert_eq!(inpState.isStandardJustReleased(KSK::E as u64), false);
+}
+
+#[test]
+fn heldForTest()
+{
+    let mut inpState = InputState::new();
+    let start = Instant::now();
+
+    inpState.beginFrame(start);
+    inpState.injectMouseButton(KMB::M2, KeyAction::Press);
+    inpState.endFrame();
+
+    inpState.beginFrame(start + Duration::from_millis(200));
+
+    assert_eq!(inpState.isMouseButtonHeldFor(KMB::M2 as u16, Duration::from_millis(100)), true);
+    assert_eq!(inpState.isMouseButtonHeldFor(KMB::M2 as u16, Duration::from_millis(500)), false);
+
+    inpState.injectMouseButton(KMB::M2, KeyAction::Release);
+
+    assert_eq!(inpState.isMouseButtonHeldFor(KMB::M2 as u16, Duration::from_millis(100)), false);
+}
+
+#[test]
+fn keyTableTest()
+{
+    // Every name table entry has to sit at its own index,
+    // otherwise name() and fromIndex() hand back the wrong key.
+    for (index, entry) in KSK_NAMES.iter().enumerate()
+    {
+        assert_eq!(entry.0 as usize, index);
+    }
+    for (index, entry) in KMK_NAMES.iter().enumerate()
+    {
+        assert_eq!(entry.0 as usize, index);
+    }
+    for (index, entry) in KMB_NAMES.iter().enumerate()
+    {
+        assert_eq!(entry.0 as usize, index);
+    }
+}
+
+#[test]
+fn fullKeyboardTest()
+{
+    let mut inpState = InputState::new();
+
+    assert_eq!(standardKeyFromGlfw(Key::F25), Some(KSK::F25));
+    assert_eq!(standardKeyFromGlfw(Key::KpEnter), Some(KSK::NUMPAD_ENTER));
+    assert_eq!(standardKeyFromGlfw(Key::Unknown), None);
+
+    inpState.handleInputEvent(InputEvent::Key(KSK::NUMPAD_EQUAL, KeyAction::Press, KeyMods::NONE));
+
+    assert_eq!(inpState.isStandardPressed(KSK::NUMPAD_EQUAL as u64), true);
+}
+
+#[test]
+fn modifierTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.injectModifier(KMK::LControl, KeyAction::Press);
+    inpState.injectKey(KSK::S, KeyAction::Press);
+
+    assert_eq!(inpState.isModifierPressed(KMK::LControl as u32), true);
+    assert_eq!(inpState.isComboPressed(KSK::S as u64, KeyMods::CONTROL), true);
+    assert_eq!(inpState.isComboPressed(KSK::S as u64, KeyMods::CONTROL | KeyMods::SHIFT), false);
+    assert_eq!(inpState.getMods().contains(KeyMods::CONTROL), true);
+
+    inpState.injectModifier(KMK::LControl, KeyAction::Release);
+
+    assert_eq!(inpState.isModifierPressed(KMK::LControl as u32), false);
+    assert_eq!(inpState.isModifierJustReleased(KMK::LControl as u32), true);
+}
+
+#[test]
+fn cursorTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.handleInputEvent(InputEvent::CursorEntered(true));
+    inpState.handleInputEvent(InputEvent::CursorMoved(100.0, 50.0));
+
+    assert_eq!(inpState.isCursorInWindow(), true);
+    assert_eq!(inpState.getCursorDelta(), (0.0, 0.0));
+
+    inpState.handleInputEvent(InputEvent::CursorMoved(110.0, 45.0));
+    inpState.handleInputEvent(InputEvent::CursorMoved(115.0, 40.0));
+
+    assert_eq!(inpState.getCursorPos(), (115.0, 40.0));
+    assert_eq!(inpState.getCursorDelta(), (15.0, -10.0));
+
+    inpState.endFrame();
+
+    assert_eq!(inpState.getCursorDelta(), (0.0, 0.0));
+    assert_eq!(inpState.getCursorPos(), (115.0, 40.0));
+}
+
+#[test]
+fn scrollTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.handleInputEvent(InputEvent::Scroll(0.0, 1.0));
+    inpState.handleInputEvent(InputEvent::Scroll(0.5, 1.0));
+
+    assert_eq!(inpState.getScrollDelta(), (0.5, 2.0));
+
+    inpState.endFrame();
+    inpState.handleInputEvent(InputEvent::Scroll(0.0, -1.0));
+
+    assert_eq!(inpState.getScrollDelta(), (0.0, -1.0));
+    assert_eq!(inpState.getScroll(), (0.5, 1.0));
+}
+
+#[test]
+fn cursorCaptureTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.handleInputEvent(InputEvent::CursorMoved(100.0, 100.0));
+    inpState.setCursorCaptured(true);
+    inpState.handleInputEvent(InputEvent::CursorMoved(375.0, 375.0));
+
+    assert_eq!(inpState.isCursorCaptured(), true);
+    assert_eq!(inpState.getCursorDelta(), (0.0, 0.0));
+
+    inpState.handleInputEvent(InputEvent::CursorMoved(-2000.0, 375.0));
+
+    assert_eq!(inpState.getCursorDelta(), (-2375.0, 0.0));
+}
+
+#[test]
+fn controllerStateTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.handleInputEvent(InputEvent::ControllerConnected(1));
+    inpState.handleInputEvent(InputEvent::ControllerButton(1, KGB::Start, KeyAction::Press));
+    inpState.handleInputEvent(InputEvent::ControllerAxis(1, KGA::RightTrigger, 1.0));
+
+    assert_eq!(inpState.connectedControllers(), vec![1]);
+    assert_eq!(inpState.isGamepadButtonJustPressed(1, KGB::Start), true);
+    assert_eq!(inpState.getGamepadAxis(1, KGA::RightTrigger), 1.0);
+
+    inpState.endFrame();
+
+    assert_eq!(inpState.isGamepadButtonJustPressed(1, KGB::Start), false);
+    assert_eq!(inpState.isGamepadButtonPressed(1, KGB::Start), true);
+
+    // Unplugging lets go of everything
+    inpState.handleInputEvent(InputEvent::ControllerDisconnected(1));
+
+    assert_eq!(inpState.isControllerConnected(1), false);
+    assert_eq!(inpState.isGamepadButtonPressed(1, KGB::Start), false);
+
+    // Slots that do not exist are ignored, not a crash
+    inpState.handleInputEvent(InputEvent::ControllerConnected(200));
+    inpState.handleInputEvent(InputEvent::ControllerButton(16, KGB::A, KeyAction::Press));
+
+    assert_eq!(inpState.isGamepadButtonPressed(16, KGB::A), false);
+    assert_eq!(inpState.getGamepadAxis(200, KGA::LeftX), 0.0);
+}
+
+#[test]
+fn focusLossTest()
+{
+    let mut inpState = InputState::new();
+
+    inpState.injectKey(KSK::W, KeyAction::Press);
+    inpState.injectModifier(KMK::LShift, KeyAction::Press);
+    inpState.injectMouseButton(KMB::M2, KeyAction::Press);
+    inpState.endFrame();
+
+    inpState.handleInputEvent(InputEvent::Focus(false));
+
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), false);
+    assert_eq!(inpState.isStandardJustReleased(KSK::W as u64), true);
+    assert_eq!(inpState.isModifierPressed(KMK::LShift as u32), false);
+    assert_eq!(inpState.isMouseButtonPressed(KMB::M2 as u16), false);
+    assert_eq!(inpState.isStandardJustReleased(KSK::S as u64), false);
+
+    // A latched Ctrl and a repeating key are forgotten too
+    let now = Instant::now();
+    inpState.sticky.enabled = true;
+    inpState.repeat = Some(RepeatSettings::fromRate(Duration::from_millis(500), 10));
+    inpState.beginFrame(now);
+    inpState.injectKey(KSK::DOWN, KeyAction::Press);
+    inpState.injectModifier(KMK::LControl, KeyAction::Press);
+    inpState.injectModifier(KMK::LControl, KeyAction::Release);
+    inpState.endFrame();
+    inpState.beginFrame(now + Duration::from_millis(600));
+
+    assert_eq!(inpState.isStandardRepeated(KSK::DOWN as u64), true);
+    assert_eq!(inpState.sticky.latched, KeyMods::CONTROL);
+
+    inpState.handleInputEvent(InputEvent::Focus(false));
+
+    assert_eq!(inpState.isStandardRepeated(KSK::DOWN as u64), false);
+    assert_eq!(inpState.sticky.latched, KeyMods::NONE);
+
+    inpState.handleInputEvent(InputEvent::Focus(true));
+    inpState.injectKey(KSK::S, KeyAction::Press);
+
+    assert_eq!(inpState.isComboPressed(KSK::S as u64, KeyMods::NONE), true);
+}
+
+/*************************************/
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use crate::input::{KSK, KSK_COUNT, standardKeyToGlfw};
+
+// What each printable key has on it under the keyboard layout in use,
+// which is how InputBinding::Logical finds "the key labelled A".
+// A KSK is where a key sits on a US keyboard, so on AZERTY the key
+// labelled A is KSK::Q. Cheap to clone, every InputState holds one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyLayout
+{
+    labels: Arc<Vec<Option<char>>>,
+}
+
+// Letters, the digit row and punctuation, the keys a layout moves around.
+// The numpad is left out since its digits never move.
+const US_LABELS: [(KSK, char); 47] =
+[
+    (KSK::A, 'A'), (KSK::B, 'B'), (KSK::C, 'C'), (KSK::D, 'D'), (KSK::E, 'E'),
+    (KSK::F, 'F'), (KSK::G, 'G'), (KSK::H, 'H'), (KSK::I, 'I'), (KSK::J, 'J'),
+    (KSK::K, 'K'), (KSK::L, 'L'), (KSK::M, 'M'), (KSK::N, 'N'), (KSK::O, 'O'),
+    (KSK::P, 'P'), (KSK::Q, 'Q'), (KSK::R, 'R'), (KSK::S, 'S'), (KSK::T, 'T'),
+    (KSK::U, 'U'), (KSK::V, 'V'), (KSK::W, 'W'), (KSK::X, 'X'), (KSK::Y, 'Y'),
+    (KSK::Z, 'Z'),
+    (KSK::ZERO, '0'), (KSK::ONE, '1'), (KSK::TWO, '2'), (KSK::THREE, '3'), (KSK::FOUR, '4'),
+    (KSK::FIVE, '5'), (KSK::SIX, '6'), (KSK::SEVEN, '7'), (KSK::EIGHT, '8'), (KSK::NINE, '9'),
+    (KSK::APOSTROPHE, '\''), (KSK::COMMA, ','), (KSK::MINUS, '-'), (KSK::PERIOD, '.'),
+    (KSK::SLASH, '/'), (KSK::SEMICOLON, ';'), (KSK::EQUAL, '='), (KSK::LEFT_BRACKET, '['),
+    (KSK::BACKSLASH, '\\'), (KSK::RIGHT_BRACKET, ']'), (KSK::GRAVE_ACCENT, '`'),
+];
+
+fn upper(label: char) -> char
+{
+    return label.to_uppercase().next().unwrap_or(label);
+}
+
+impl KeyLayout
+{
+    // US QWERTY, where every key has on it what its KSK says
+    pub fn us() -> KeyLayout
+    {
+        return KeyLayout::fromLabels(&[]);
+    }
+
+    // US QWERTY with some keys labelled differently
+    pub fn fromLabels(labels: &[(KSK, char)]) -> KeyLayout
+    {
+        let mut ret = vec![None; KSK_COUNT];
+        for (key, label) in US_LABELS.iter().chain(labels.iter())
+        {
+            ret[*key as usize] = Some(upper(*label));
+        }
+
+        return KeyLayout
+        {
+            labels: Arc::new(ret),
+        };
+    }
+
+    // Asks GLFW what the layout in use prints on each key.
+    // Needs GLFW initialised, and goes stale if the player switches
+    // layouts, so ask again when the window gets focus back.
+    pub fn current() -> KeyLayout
+    {
+        let mut labels = Vec::new();
+        for (key, _) in US_LABELS.iter()
+        {
+            // Dead keys can come back as more than one character
+            if let Some(name) = glfw::get_key_name(Some(standardKeyToGlfw(*key)), None)
+            {
+                let mut chars = name.chars();
+                if let (Some(label), None) = (chars.next(), chars.next())
+                {
+                    labels.push((*key, label));
+                }
+            }
+        }
+
+        return KeyLayout::fromLabels(&labels);
+    }
+
+    pub fn labelOf(&self, key: KSK) -> Option<char>
+    {
+        return self.labels[key as usize];
+    }
+
+    // The key labelled label, if there is one.
+    // Letters match either case.
+    pub fn keyFor(&self, label: char) -> Option<KSK>
+    {
+        let index = self.labels.iter().position(|existing| *existing == Some(upper(label)))?;
+        return KSK::fromIndex(index);
+    }
+}
+
+// What the current keyboard layout calls a key.
+// GLFW only names printable keys, everything else gets its KSK name.
+pub fn keyDisplayName(key: KSK) -> String
+{
+    return match glfw::get_key_name(Some(standardKeyToGlfw(key)), None)
+    {
+        Some(name) => name.to_uppercase(),
+        None => key.name().to_string(),
+    };
+}
+
+// The same for a key with no KSK, see InputEvent::Physical
+pub fn scancodeDisplayName(scancode: i32) -> String
+{
+    return match glfw::get_key_name(None, Some(scancode as glfw::Scancode))
+    {
+        Some(name) => name.to_uppercase(),
+        None => format!("Key {}", scancode),
+    };
+}
+
+/*************************************/
+// Layout tests
+
+#[test]
+fn physicalBindingTest()
+{
+    use glfw::{Action, Modifiers};
+    use crate::action::{ActionMap, InputBinding};
+    use crate::event::{InputEvent, KeyAction};
+
+    let mut actions = ActionMap::new();
+    let mut inpState = crate::input::InputState::new();
+
+    // The ISO key next to left shift, which GLFW has no Key for
+    let iso = glfw::WindowEvent::Key(glfw::Key::Unknown, 94, Action::Press, Modifiers::empty());
+
+    actions.bind("crouch", InputBinding::fromName("@94").unwrap());
+    inpState.handleInputEvent(InputEvent::fromGlfw(&iso).unwrap());
+
+    assert_eq!(actions.isActionPressed("crouch", &inpState), true);
+    assert_eq!(InputBinding::Physical(94).name(), "@94");
+
+    // Held like any other key, not from the first frame
+    assert_eq!(actions.isActionHeld("crouch", &inpState), false);
+
+    inpState.handleInputEvent(InputEvent::Physical(94, KeyAction::Repeat));
+
+    assert_eq!(actions.isActionHeld("crouch", &inpState), true);
+
+    inpState.handleInputEvent(InputEvent::Physical(94, KeyAction::Release));
+
+    assert_eq!(actions.isActionPressed("crouch", &inpState), false);
+}
+
+#[test]
+fn logicalBindingTest()
+{
+    use crate::action::{ActionMap, InputBinding};
+    use crate::event::KeyAction;
+
+    let mut actions = ActionMap::new();
+    let mut inpState = crate::input::InputState::new();
+
+    // Forward is whatever key says Z, which is where W is on AZERTY
+    actions.bind("move_forward", InputBinding::fromName("label:Z").unwrap());
+    inpState.injectKey(KSK::W, KeyAction::Press);
+
+    assert_eq!(actions.isActionPressed("move_forward", &inpState), false);
+
+    inpState.setLayout(KeyLayout::fromLabels(&[(KSK::Q, 'a'), (KSK::A, 'q'), (KSK::W, 'z'), (KSK::Z, 'w')]));
+
+    assert_eq!(inpState.layout.keyFor('z'), Some(KSK::W));
+    assert_eq!(actions.isActionPressed("move_forward", &inpState), true);
+    assert_eq!(InputBinding::Logical('Z').name(), "label:Z");
+    assert_eq!(KeyLayout::us().labelOf(KSK::SEMICOLON), Some(';'));
+}
+
+/*************************************/
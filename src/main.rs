@@ -1,21 +1,57 @@
 extern crate glfw;
 
-use std::time::{Duration};
+use std::time::{Duration, Instant};
 use std::thread;
+use std::env;
+use std::sync::{Arc, Mutex};
 mod tick;
 mod input;
+mod event;
+mod action;
+mod bindings;
+mod replay;
+mod cursor;
+mod gamepad;
+mod axis;
+mod context;
+mod combo;
+mod text;
+mod snapshot;
+mod inject;
+mod gesture;
+mod layout;
+mod access;
+mod systems;
+mod route;
 
 use glfw::{Action, Context, Key};
-use tick::{tickPhysics, tickEngine};
+use tick::{Ticker, TickMode, StopError, PHYS_TICK, ENGINE_TICK};
 use input::{handleWindowEvent, InputState};
+use event::{InputEvent, EngineEvent};
+use bindings::loadBindingsOrDefault;
+use replay::InputRecorder;
+use cursor::CursorController;
+use gamepad::ControllerPoller;
+use context::{ContextStack, InputContext, ConsumeMode};
+use text::TextInput;
+use snapshot::InputPublisher;
+use systems::EngineContext;
+use route::routeInputEvent;
+use layout::KeyLayout;
+
+const BINDINGS_FILE: &str = "bindings.toml";
 
 fn main()
 {
 
-    let mut phys = tickPhysics::new();
-    let mut eng = tickEngine::new();
-    phys.start();
-    eng.start();
+    // Each tick thread gets its own copy of every frame's input
+    let mut publisher = InputPublisher::new();
+    let mut phys = Ticker::new("Physics", PHYS_TICK, TickMode::Thread);
+    let mut eng = Ticker::new("Engine", ENGINE_TICK, TickMode::Thread);
+    // Shared by the systems on both threads
+    let engineContext = Arc::new(Mutex::new(EngineContext::new()));
+    phys.start(publisher.subscribe(phys.clock.clone()), engineContext.clone());
+    eng.start(publisher.subscribe(eng.clock.clone()), engineContext.clone());
     
     // Temporary code: https://github.com/PistonDevelopers/glfw-rs#using-glfw-rs
 
@@ -27,6 +63,15 @@ fn main()
 
     window.set_key_polling(true);
     window.set_mouse_button_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_cursor_enter_polling(true);
+    window.set_scroll_polling(true);
+    window.set_focus_polling(true);
+    window.set_char_polling(true);
+    window.set_iconify_polling(true);
+    window.set_close_polling(true);
+    window.set_size_polling(true);
+    window.set_framebuffer_size_polling(true);
     window.make_current();
 
     
@@ -36,19 +81,108 @@ fn main()
     // }
 
     let mut inpState = InputState::new();
+    let actions = loadBindingsOrDefault(BINDINGS_FILE);
     let mut quit = false;
+    let mut frame: u64 = 0;
+
+    // Pass --record <file> to save this session's input for replaying later
+    let args: Vec<String> = env::args().collect();
+    let recordPath = args.iter().position(|arg| arg == "--record").and_then(|i| args.get(i + 1)).cloned();
+    let mut recorder = InputRecorder::new();
+    let recordStart = Instant::now();
+    let mut cursor = CursorController::new(glfw.supports_raw_motion());
+    let mut controllers = ControllerPoller::new();
+
+    // Gameplay sits at the bottom, menus and the console get pushed over it
+    let mut contexts = ContextStack::new();
+    inpState.setLayout(KeyLayout::current());
+    contexts.setLayout(inpState.layout.clone());
+    contexts.push(InputContext::new("gameplay", actions, ConsumeMode::Pass));
+
+    // Off until something like a chat box or the console turns it on
+    let mut text = TextInput::new();
 
     'game: loop
     {
 
-        // Get cursor position
-        // let (xpos, ypos) = window.get_cursor_pos();
-        // println!("xpos: {} | ypos: {}", xpos, ypos);
-        
+        let now = Instant::now();
+        inpState.beginFrame(now);
+        contexts.beginFrame(now);
+        if recordPath.is_some()
+        {
+            recorder.recordFrame(frame, now.duration_since(recordStart));
+        }
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
-            quit = handleWindowEvent(&mut window, event, &mut inpState);
+            // Recorded before routing, a replay routes it the same way.
+            // Only what gets down to gameplay goes to the tick threads.
+            let routed = match InputEvent::fromGlfw(&event)
+            {
+                Some(inputEvent) =>
+                {
+                    if recordPath.is_some()
+                    {
+                        recorder.record(frame, inputEvent);
+                    }
+                    let routed = routeInputEvent(inputEvent, &mut text, &mut window, &mut contexts, &mut inpState);
+                    if routed
+                    {
+                        publisher.record(inputEvent);
+                    }
+                    routed
+                },
+                None => true,
+            };
+
+            if routed
+            {
+                // Once something asks to quit a later event must not undo it
+                quit = handleWindowEvent(&mut window, event.clone()) || quit;
+            }
+            if let Some(engineEvent) = EngineEvent::fromGlfw(&event)
+            {
+                match engineEvent
+                {
+                    EngineEvent::FocusChanged(focused) =>
+                    {
+                        cursor.focusChanged(focused);
+                        // The layout may have been switched while away
+                        if focused
+                        {
+                            inpState.setLayout(KeyLayout::current());
+                            contexts.setLayout(inpState.layout.clone());
+                        }
+                    },
+                    EngineEvent::CloseRequested =>          {quit = true;},
+                    _ => {},
+                }
+                publisher.recordEngineEvent(engineEvent);
+            }
         }
+        let captured = inpState.isCursorCaptured();
+        cursor.apply(&mut window, &mut inpState);
+        if inpState.isCursorCaptured() != captured
+        {
+            contexts.setCursorCaptured(inpState.isCursorCaptured());
+        }
+
+        // Controllers are polled rather than sent as window events,
+        // which is also how plugging and unplugging gets noticed
+        for inputEvent in controllers.poll(&mut glfw)
+        {
+            if recordPath.is_some()
+            {
+                recorder.record(frame, inputEvent);
+            }
+            if routeInputEvent(inputEvent, &mut text, &mut window, &mut contexts, &mut inpState)
+            {
+                publisher.record(inputEvent);
+            }
+        }
+
+        // A tick thread that panicked takes the game down with it
+        phys.propagatePanic();
+        eng.propagatePanic();
 
         // This line lets me quit for reasons other than
         // the window needing to close
@@ -58,11 +192,45 @@ fn main()
         {
             break 'game;
         }
+
+        // The tick threads run gameplay, so they get its view of the
+        // input rather than everything, menus and all
+        match contexts.bottom()
+        {
+            Some(gameplay) => publisher.publish(frame, &gameplay.state),
+            None => publisher.publish(frame, &inpState),
+        }
+        inpState.endFrame();
+        contexts.endFrame();
+        frame += 1;
+    }
+
+    if let Some(path) = recordPath
+    {
+        recorder.save(&path).expect("Failed to save input recording.");
     }
 
     window.close();
-    phys.stop();
-    eng.stop();
+
+    // Both get stopped before a panic from either is passed on
+    let mut panicked = None;
+    for result in vec![phys.stop(), eng.stop()]
+    {
+        match result
+        {
+            Err(StopError::Panicked(name, payload)) =>
+            {
+                println!("{} ticker panicked", name);
+                panicked = panicked.or(Some(payload));
+            },
+            Err(err) => println!("{}", err),
+            Ok(()) => {},
+        }
+    }
+    if let Some(payload) = panicked
+    {
+        std::panic::resume_unwind(payload);
+    }
     
     println!("Exiting!");
 
@@ -0,0 +1,588 @@
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::event::{InputEvent, KeyAction, KeyMods};
+use crate::input::{InputState, KSK, KMK, KMB};
+use crate::gamepad::{KGB, KGA, MAX_CONTROLLERS};
+use crate::context::ContextStack;
+use crate::text::TextInput;
+use crate::route::routeInputEvent;
+
+////////////////////////////////////////////////
+// Replay file layout
+//
+// Header:  "KREC" followed by a one byte version.
+// Events:  tick (u64, little endian), kind (u8), then the
+//          payload for that kind of event.
+//
+// Key, Modifier and MouseButton events (kinds 0, 1 and 2) have a
+// three byte payload: the index of the key or button inside its
+// enum, the action, then the modifier bits (always 0 for kind 1).
+//
+// CursorMoved and Scroll events (kinds 3 and 4) carry two f64s,
+// CursorEntered (kind 5) carries a single 0 or 1 byte.
+//
+// ControllerConnected and ControllerDisconnected (kinds 6 and 7)
+// carry the controller slot. ControllerButton (kind 8) carries the
+// slot, button and action, ControllerAxis (kind 9) the slot, axis
+// and an f32. Char (kind 10) carries the character as a u32
+// followed by the modifier bits. Focus (kind 11) carries a
+// single 0 or 1 byte. Physical (kind 12) carries the scancode
+// as an i32 followed by the action.
+//
+// Frame times (kind 13) are not events. They carry a u64 of
+// nanoseconds since recording started, and come before the events
+// of their tick so a replay sees the same clock the game did.
+// Only ticks with events and the last tick get one, the ticks in
+// between are spread evenly across the gaps.
+
+const REPLAY_MAGIC: &[u8; 4] = b"KREC";
+const REPLAY_VERSION: u8 = 10;
+const REPLAY_HEADER_SIZE: usize = 5;
+const FRAME_TIME_KIND: u8 = 13;
+////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordedEvent
+{
+    pub tick: u64,
+    pub event: InputEvent,
+}
+
+// Collects every input event the engine handles, along with the
+// tick it arrived on, and when the ticks with events started.
+pub struct InputRecorder
+{
+    pub events: Vec<RecordedEvent>,
+    pub frame_times: Vec<(u64, Duration)>,
+}
+
+// Plays a recording back into an InputState one tick at a time.
+// No window is needed, so this also works inside tests.
+pub struct InputReplay
+{
+    pub events: Vec<RecordedEvent>,
+    pub frame_times: Vec<(u64, Duration)>,
+    // Recorded frame times are offsets from this
+    pub start: Instant,
+    next: usize,
+    in_frame: bool,
+    // Pastes during a replay get whatever was copied during it
+    clipboard: String,
+}
+
+fn actionToByte(action: KeyAction) -> u8
+{
+    return match action
+    {
+        KeyAction::Release =>  0,
+        KeyAction::Press =>    1,
+        KeyAction::Repeat =>   2,
+    };
+}
+
+fn actionFromByte(byte: u8) -> Option<KeyAction>
+{
+    return match byte
+    {
+        0 => Some(KeyAction::Release),
+        1 => Some(KeyAction::Press),
+        2 => Some(KeyAction::Repeat),
+        _ => None,
+    };
+}
+
+fn invalidData(message: &str) -> io::Error
+{
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+// How many bytes follow the kind byte for each kind of event
+fn payloadSize(kind: u8) -> Option<usize>
+{
+    return match kind
+    {
+        0 | 1 | 2 =>    Some(3),
+        3 | 4 =>        Some(16),
+        5 | 6 | 7 =>    Some(1),
+        8 =>            Some(3),
+        9 =>            Some(6),
+        10 =>           Some(5),
+        11 =>           Some(1),
+        12 =>           Some(5),
+        13 =>           Some(8),
+        _ =>            None,
+    };
+}
+
+fn readF64(bytes: &[u8]) -> f64
+{
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&bytes[0..8]);
+    return f64::from_le_bytes(raw);
+}
+
+fn readF32(bytes: &[u8]) -> f32
+{
+    let mut raw = [0u8; 4];
+    raw.copy_from_slice(&bytes[0..4]);
+    return f32::from_le_bytes(raw);
+}
+
+fn writeEvent(out: &mut Vec<u8>, event: &InputEvent)
+{
+    match *event
+    {
+        InputEvent::Key(key, action, mods) =>
+        {
+            out.extend_from_slice(&[0, key as u8, actionToByte(action), mods.bits]);
+        },
+        InputEvent::Modifier(key, action) =>
+        {
+            out.extend_from_slice(&[1, key as u8, actionToByte(action), 0]);
+        },
+        InputEvent::MouseButton(button, action, mods) =>
+        {
+            out.extend_from_slice(&[2, button as u8, actionToByte(action), mods.bits]);
+        },
+        InputEvent::CursorMoved(x, y) =>
+        {
+            out.push(3);
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+        },
+        InputEvent::Scroll(x, y) =>
+        {
+            out.push(4);
+            out.extend_from_slice(&x.to_le_bytes());
+            out.extend_from_slice(&y.to_le_bytes());
+        },
+        InputEvent::CursorEntered(entered) =>
+        {
+            out.push(5);
+            out.push(entered as u8);
+        },
+        InputEvent::ControllerConnected(slot) =>
+        {
+            out.extend_from_slice(&[6, slot]);
+        },
+        InputEvent::ControllerDisconnected(slot) =>
+        {
+            out.extend_from_slice(&[7, slot]);
+        },
+        InputEvent::ControllerButton(slot, button, action) =>
+        {
+            out.extend_from_slice(&[8, slot, button as u8, actionToByte(action)]);
+        },
+        InputEvent::ControllerAxis(slot, axis, value) =>
+        {
+            out.extend_from_slice(&[9, slot, axis as u8]);
+            out.extend_from_slice(&value.to_le_bytes());
+        },
+        InputEvent::Char(c, mods) =>
+        {
+            out.push(10);
+            out.extend_from_slice(&(c as u32).to_le_bytes());
+            out.push(mods.bits);
+        },
+        InputEvent::Focus(focused) =>
+        {
+            out.push(11);
+            out.push(focused as u8);
+        },
+        InputEvent::Physical(scancode, action) =>
+        {
+            out.push(12);
+            out.extend_from_slice(&scancode.to_le_bytes());
+            out.push(actionToByte(action));
+        },
+    }
+}
+
+fn writeFrameTime(out: &mut Vec<u8>, tick: u64, time: Duration)
+{
+    out.extend_from_slice(&tick.to_le_bytes());
+    out.push(FRAME_TIME_KIND);
+    out.extend_from_slice(&(time.as_nanos() as u64).to_le_bytes());
+}
+
+// Reads one event starting at bytes[0], returning it
+// along with how many bytes it took up.
+fn readEvent(bytes: &[u8]) -> io::Result<(InputEvent, usize)>
+{
+    let size = match bytes.first().and_then(|kind| payloadSize(*kind))
+    {
+        Some(size) => size,
+        None => {return Err(invalidData("replay file contains an unknown event"));},
+    };
+    if bytes.len() < 1 + size
+    {
+        return Err(invalidData("replay file is truncated"));
+    }
+
+    let body = &bytes[1..1 + size];
+    if (6..=9).contains(&bytes[0]) && body[0] as usize >= MAX_CONTROLLERS
+    {
+        return Err(invalidData("replay file contains an out of range controller slot"));
+    }
+
+    let event = match bytes[0]
+    {
+        0 => actionFromByte(body[1]).and_then(|action|
+                KSK::fromIndex(body[0] as usize).map(|key| InputEvent::Key(key, action, KeyMods { bits: body[2] }))),
+        1 => actionFromByte(body[1]).and_then(|action|
+                KMK::fromIndex(body[0] as usize).map(|key| InputEvent::Modifier(key, action))),
+        2 => actionFromByte(body[1]).and_then(|action|
+                KMB::fromIndex(body[0] as usize).map(|button| InputEvent::MouseButton(button, action, KeyMods { bits: body[2] }))),
+        3 => Some(InputEvent::CursorMoved(readF64(&body[0..8]), readF64(&body[8..16]))),
+        4 => Some(InputEvent::Scroll(readF64(&body[0..8]), readF64(&body[8..16]))),
+        5 => Some(InputEvent::CursorEntered(body[0] != 0)),
+        6 => Some(InputEvent::ControllerConnected(body[0])),
+        7 => Some(InputEvent::ControllerDisconnected(body[0])),
+        8 => actionFromByte(body[2]).and_then(|action|
+                KGB::fromIndex(body[1] as usize).map(|button| InputEvent::ControllerButton(body[0], button, action))),
+        9 => KGA::fromIndex(body[1] as usize).map(|axis| InputEvent::ControllerAxis(body[0], axis, readF32(&body[2..6]))),
+        10 =>
+        {
+            let mut raw = [0u8; 4];
+            raw.copy_from_slice(&body[0..4]);
+            char::from_u32(u32::from_le_bytes(raw)).map(|c| InputEvent::Char(c, KeyMods { bits: body[4] }))
+        },
+        11 => Some(InputEvent::Focus(body[0] != 0)),
+        12 =>
+        {
+            let mut raw = [0u8; 4];
+            raw.copy_from_slice(&body[0..4]);
+            actionFromByte(body[4]).map(|action| InputEvent::Physical(i32::from_le_bytes(raw), action))
+        },
+        _ => None,
+    };
+
+    return match event
+    {
+        Some(event) => Ok((event, 1 + size)),
+        None => Err(invalidData("replay file contains an unknown event")),
+    };
+}
+
+impl InputRecorder
+{
+    pub fn new() -> InputRecorder
+    {
+        InputRecorder
+        {
+            events: Vec::new(),
+            frame_times: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, event: InputEvent)
+    {
+        self.events.push(RecordedEvent { tick, event });
+    }
+
+    // Call at the top of each tick, time is how long since recording started.
+    // The previous tick's time is only kept if it had events.
+    pub fn recordFrame(&mut self, tick: u64, time: Duration)
+    {
+        if let Some(last) = self.frame_times.last().map(|frameTime| frameTime.0)
+        {
+            let hadEvents = self.events.iter().rev().take_while(|recorded| recorded.tick >= last).any(|recorded| recorded.tick == last);
+            if !hadEvents
+            {
+                self.frame_times.pop();
+            }
+        }
+        self.frame_times.push((tick, time));
+    }
+
+    pub fn toBytes(&self) -> Vec<u8>
+    {
+        let mut ret = Vec::new();
+
+        ret.extend_from_slice(REPLAY_MAGIC);
+        ret.push(REPLAY_VERSION);
+
+        // Each tick's frame time goes ahead of its events
+        let mut frameTimes = self.frame_times.iter().peekable();
+        for recorded in &self.events
+        {
+            while let Some((tick, time)) = frameTimes.next_if(|frameTime| frameTime.0 <= recorded.tick)
+            {
+                writeFrameTime(&mut ret, *tick, *time);
+            }
+            ret.extend_from_slice(&recorded.tick.to_le_bytes());
+            writeEvent(&mut ret, &recorded.event);
+        }
+        for (tick, time) in frameTimes
+        {
+            writeFrameTime(&mut ret, *tick, *time);
+        }
+
+        return ret;
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()>
+    {
+        return fs::write(path, self.toBytes());
+    }
+}
+
+impl InputReplay
+{
+    pub fn fromBytes(bytes: &[u8]) -> io::Result<InputReplay>
+    {
+        if bytes.len() < REPLAY_HEADER_SIZE || &bytes[0..4] != REPLAY_MAGIC
+        {
+            return Err(invalidData("not a Kestrel replay file"));
+        }
+        if bytes[4] != REPLAY_VERSION
+        {
+            return Err(invalidData("unsupported replay version"));
+        }
+
+        let mut events = Vec::new();
+        let mut frameTimes = Vec::new();
+        let mut offset = REPLAY_HEADER_SIZE;
+        while offset < bytes.len()
+        {
+            if bytes.len() - offset < 8
+            {
+                return Err(invalidData("replay file is truncated"));
+            }
+
+            let mut tick = [0u8; 8];
+            tick.copy_from_slice(&bytes[offset..offset + 8]);
+            offset += 8;
+
+            if bytes.get(offset) == Some(&FRAME_TIME_KIND)
+            {
+                if bytes.len() - offset < 9
+                {
+                    return Err(invalidData("replay file is truncated"));
+                }
+                let mut time = [0u8; 8];
+                time.copy_from_slice(&bytes[offset + 1..offset + 9]);
+                offset += 9;
+
+                frameTimes.push((u64::from_le_bytes(tick), Duration::from_nanos(u64::from_le_bytes(time))));
+                continue;
+            }
+
+            let (event, size) = readEvent(&bytes[offset..])?;
+            offset += size;
+
+            events.push(RecordedEvent { tick: u64::from_le_bytes(tick), event });
+        }
+
+        return Ok(InputReplay
+        {
+            events,
+            frame_times: frameTimes,
+            start: Instant::now(),
+            next: 0,
+            in_frame: false,
+            clipboard: String::new(),
+        });
+    }
+
+    pub fn load(path: &str) -> io::Result<InputReplay>
+    {
+        let bytes = fs::read(path)?;
+        return InputReplay::fromBytes(&bytes);
+    }
+
+    // When tick started. Ticks without a recorded time get one
+    // in between the recorded times either side of them.
+    pub fn frameTime(&self, tick: u64) -> Instant
+    {
+        // Recording starts at tick 0
+        let before = self.frame_times.iter().take_while(|frameTime| frameTime.0 <= tick).last().cloned().unwrap_or((0, Duration::from_secs(0)));
+        let after = self.frame_times.iter().find(|frameTime| frameTime.0 > tick);
+
+        let time = match after
+        {
+            Some(after) =>
+            {
+                let progress = (tick - before.0) as f64 / (after.0 - before.0) as f64;
+                before.1 + after.1.saturating_sub(before.1).mul_f64(progress)
+            },
+            None => before.1,
+        };
+        return self.start + time;
+    }
+
+    // Routes every event recorded on or before tick that has not been
+    // played yet, as a frame of its own, through the same text entry and
+    // contexts the game uses. The last tick played is ended first, so its
+    // just pressed flags last until the next call the same way they last
+    // until the bottom of a real frame.
+    pub fn playTick(&mut self, tick: u64, inState: &mut InputState, text: &mut TextInput, contexts: &mut ContextStack)
+    {
+        if self.in_frame
+        {
+            inState.endFrame();
+            contexts.endFrame();
+        }
+        let now = self.frameTime(tick);
+        inState.beginFrame(now);
+        contexts.beginFrame(now);
+        self.in_frame = true;
+
+        while self.next < self.events.len() && self.events[self.next].tick <= tick
+        {
+            routeInputEvent(self.events[self.next].event, text, &mut self.clipboard, contexts, inState);
+            self.next += 1;
+        }
+    }
+
+    pub fn isFinished(&self) -> bool
+    {
+        return self.next >= self.events.len();
+    }
+
+    pub fn lastTick(&self) -> u64
+    {
+        return self.events.last().map(|event| event.tick).unwrap_or(0);
+    }
+}
+
+/*************************************/
+// Replay tests
+
+#[test]
+fn replayRoundTripTest()
+{
+    let mut recorder = InputRecorder::new();
+
+    recorder.recordFrame(0, Duration::from_millis(0));
+    recorder.record(0, InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE));
+    recorder.recordFrame(3, Duration::from_millis(48));
+    recorder.record(3, InputEvent::MouseButton(KMB::M2, KeyAction::Press, KeyMods::SHIFT));
+    recorder.record(3, InputEvent::Modifier(KMK::LShift, KeyAction::Repeat));
+    recorder.record(4, InputEvent::CursorMoved(120.5, -3.25));
+    recorder.record(4, InputEvent::Scroll(0.0, 1.0));
+    recorder.record(4, InputEvent::CursorEntered(false));
+    recorder.record(4, InputEvent::ControllerConnected(3));
+    recorder.record(4, InputEvent::ControllerButton(3, KGB::DpadLeft, KeyAction::Press));
+    recorder.record(4, InputEvent::ControllerAxis(3, KGA::LeftY, -0.75));
+    recorder.record(5, InputEvent::ControllerDisconnected(3));
+    recorder.record(5, InputEvent::Char('ß', KeyMods::SHIFT));
+    recorder.record(5, InputEvent::Physical(94, KeyAction::Press));
+    recorder.record(5, InputEvent::Focus(false));
+    recorder.record(5, InputEvent::Key(KSK::W, KeyAction::Release, KeyMods::CONTROL | KeyMods::ALT));
+
+    let replay = InputReplay::fromBytes(&recorder.toBytes()).unwrap();
+
+    assert_eq!(replay.events, recorder.events);
+    assert_eq!(replay.frame_times, recorder.frame_times);
+    assert_eq!(replay.lastTick(), 5);
+}
+
+#[test]
+fn replayPlayTickTest()
+{
+    let mut recorder = InputRecorder::new();
+
+    recorder.record(1, InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE));
+    recorder.record(4, InputEvent::Key(KSK::W, KeyAction::Release, KeyMods::NONE));
+
+    let mut replay = InputReplay::fromBytes(&recorder.toBytes()).unwrap();
+    let mut inpState = InputState::new();
+    let mut text = TextInput::new();
+    let mut contexts = ContextStack::new();
+
+    replay.playTick(0, &mut inpState, &mut text, &mut contexts);
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), false);
+
+    replay.playTick(1, &mut inpState, &mut text, &mut contexts);
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), true);
+
+    replay.playTick(3, &mut inpState, &mut text, &mut contexts);
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), true);
+
+    replay.playTick(4, &mut inpState, &mut text, &mut contexts);
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), false);
+    assert_eq!(replay.isFinished(), true);
+}
+
+#[test]
+fn replayBadFileTest()
+{
+    assert!(InputReplay::fromBytes(b"nope").is_err());
+    assert!(InputReplay::fromBytes(b"KREC\x01").is_err());
+    assert!(InputReplay::fromBytes(b"KREC\x0a\x00").is_err());
+
+    // A connect event for slot 16, one past the last slot
+    let err = InputReplay::fromBytes(b"KREC\x0a\x00\x00\x00\x00\x00\x00\x00\x00\x06\x10").err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn replayFrameTest()
+{
+    let mut recorder = InputRecorder::new();
+
+    recorder.recordFrame(0, Duration::from_millis(0));
+    recorder.recordFrame(1, Duration::from_millis(16));
+    recorder.record(1, InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE));
+    for tick in 2..=11
+    {
+        recorder.recordFrame(tick, Duration::from_millis(16 + (tick - 1) * 100));
+    }
+
+    // Only the tick with the press and the last tick are kept
+    assert_eq!(recorder.frame_times, vec![(1, Duration::from_millis(16)), (11, Duration::from_millis(1016))]);
+
+    let mut replay = InputReplay::fromBytes(&recorder.toBytes()).unwrap();
+    let mut inpState = InputState::new();
+    let mut text = TextInput::new();
+    let mut contexts = ContextStack::new();
+
+    replay.playTick(0, &mut inpState, &mut text, &mut contexts);
+    replay.playTick(1, &mut inpState, &mut text, &mut contexts);
+
+    assert_eq!(inpState.isStandardJustPressed(KSK::W as u64), true);
+    assert_eq!(inpState.frame_time, replay.start + Duration::from_millis(16));
+
+    replay.playTick(6, &mut inpState, &mut text, &mut contexts);
+
+    // Halfway between the recorded times either side
+    assert_eq!(inpState.frame_time.duration_since(replay.start), Duration::from_millis(516));
+
+    replay.playTick(11, &mut inpState, &mut text, &mut contexts);
+
+    // Held for the recorded second, not however long the test took
+    assert_eq!(inpState.isStandardJustPressed(KSK::W as u64), false);
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), true);
+    assert_eq!(inpState.frame_time.duration_since(replay.start), Duration::from_millis(1016));
+}
+
+#[test]
+fn replayTypedTest()
+{
+    use crate::action::ActionMap;
+    use crate::context::{InputContext, ConsumeMode};
+
+    let mut recorder = InputRecorder::new();
+
+    recorder.record(0, InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE));
+    recorder.record(0, InputEvent::Char('w', KeyMods::NONE));
+
+    let mut replay = InputReplay::fromBytes(&recorder.toBytes()).unwrap();
+    let mut inpState = InputState::new();
+    let mut text = TextInput::new();
+    let mut contexts = ContextStack::new();
+    contexts.push(InputContext::new("gameplay", ActionMap::defaults(), ConsumeMode::Pass));
+
+    // The chat box was open when this was recorded
+    text.enable();
+    replay.playTick(0, &mut inpState, &mut text, &mut contexts);
+
+    assert_eq!(text.buffer, "w");
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), false);
+    assert_eq!(contexts.isActionPressed("gameplay", "move_forward"), false);
+}
+
+/*************************************/
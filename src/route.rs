@@ -0,0 +1,67 @@
+use crate::context::ContextStack;
+use crate::event::{InputEvent, KeyAction};
+use crate::input::InputState;
+use crate::text::{Clipboard, TextInput};
+
+// Takes one event through text entry, then the context stack and
+// inState. Live input and replays both come through here, so a
+// replay ends up wherever the live input did.
+// Returns true if the event made it down to the bottom context, false
+// if text entry used it or a context above the bottom one consumed it.
+pub fn routeInputEvent<C: Clipboard>(event: InputEvent, text: &mut TextInput, clipboard: &mut C, contexts: &mut ContextStack, inState: &mut InputState) -> bool
+{
+    // While typing, text entry gets first go at keys and characters,
+    // and whatever it uses goes no further, so typing "w" into a
+    // chat box does not walk forward. A key let go while typing
+    // may have gone down before typing started, so its release
+    // still gets through to the game.
+    let typed = match event
+    {
+        InputEvent::Key(key, KeyAction::Release, _) if inState.isStandardPressed(key as u64) =>
+        {
+            text.handleInputEvent(event, clipboard);
+            false
+        },
+        _ => text.handleInputEvent(event, clipboard),
+    };
+    if typed
+    {
+        return false;
+    }
+
+    inState.handleInputEvent(event);
+    return contexts.handleInputEvent(event);
+}
+
+/*************************************/
+// Route tests
+
+#[test]
+fn routeTypedTest()
+{
+    use crate::action::ActionMap;
+    use crate::context::{InputContext, ConsumeMode};
+    use crate::event::KeyMods;
+    use crate::input::KSK;
+
+    let mut text = TextInput::new();
+    let mut clipboard = String::new();
+    let mut contexts = ContextStack::new();
+    let mut inpState = InputState::new();
+    contexts.push(InputContext::new("gameplay", ActionMap::defaults(), ConsumeMode::Pass));
+
+    // Held before the chat box opened
+    routeInputEvent(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE), &mut text, &mut clipboard, &mut contexts, &mut inpState);
+    text.enable();
+
+    assert_eq!(routeInputEvent(InputEvent::Key(KSK::D, KeyAction::Press, KeyMods::NONE), &mut text, &mut clipboard, &mut contexts, &mut inpState), false);
+    assert_eq!(routeInputEvent(InputEvent::Char('d', KeyMods::NONE), &mut text, &mut clipboard, &mut contexts, &mut inpState), false);
+    assert_eq!(routeInputEvent(InputEvent::Key(KSK::W, KeyAction::Release, KeyMods::NONE), &mut text, &mut clipboard, &mut contexts, &mut inpState), true);
+
+    assert_eq!(text.buffer, "d");
+    assert_eq!(inpState.isStandardPressed(KSK::D as u64), false);
+    assert_eq!(inpState.isStandardPressed(KSK::W as u64), false);
+    assert_eq!(contexts.isActionPressed("gameplay", "move_forward"), false);
+}
+
+/*************************************/
@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::event::{InputEvent, EngineEvent};
+use crate::input::InputState;
+use crate::tick::TickClock;
+
+// Most frames a subscriber can have waiting. Past this the oldest
+// is dropped, so a stalled tick thread costs a bounded amount of
+// memory and each publish a bounded amount of work.
+const MAX_QUEUED_FRAMES: usize = 64;
+
+// Everything that happened to the input during one frame of the main
+// thread, along with what InputState looked like at the end of it.
+// Frames are never changed once published, so any thread can hold one.
+pub struct InputFrame
+{
+    pub frame: u64,
+    pub events: Vec<InputEvent>,
+    pub engine_events: Vec<EngineEvent>,
+    pub state: InputState,
+}
+
+// What a tick thread sees for one of its ticks: every event from the
+// frames meant for this tick or earlier, oldest first, and the state
+// at the end of the newest of those frames. A tick with no frames
+// meant for it gets no events and the same state as the tick before.
+pub struct TickInput
+{
+    pub tick: u64,
+    // The frames these events came from, None if there were none
+    pub frames: Option<(u64, u64)>,
+    pub events: Vec<InputEvent>,
+    pub engine_events: Vec<EngineEvent>,
+    pub state: Option<Arc<InputFrame>>,
+    // Set when frames were dropped since the last poll because the
+    // queue was full. The state is still current, but events from
+    // the dropped frames are missing, so a press may never be seen.
+    pub overflowed: bool,
+}
+
+// A frame waiting in a subscriber's queue, with the tick it is for
+type QueuedFrame = (u64, Arc<InputFrame>);
+
+struct FrameQueue
+{
+    frames: VecDeque<QueuedFrame>,
+    overflowed: bool,
+}
+
+struct Subscription
+{
+    clock: Arc<TickClock>,
+    queue: Weak<Mutex<FrameQueue>>,
+}
+
+// Lives on the main thread. Collects a frame's events as they are
+// handled and hands the finished frame to every subscriber.
+pub struct InputPublisher
+{
+    subscribers: Vec<Subscription>,
+    pending: Vec<InputEvent>,
+    pending_engine: Vec<EngineEvent>,
+}
+
+// Lives on a tick thread. Each published frame is stamped with the
+// tick it is for when it is published, the next tick the subscriber's
+// clock has not run yet, so which tick sees a frame does not depend
+// on when the tick thread gets around to polling.
+pub struct InputSubscriber
+{
+    queue: Arc<Mutex<FrameQueue>>,
+    latest: Option<Arc<InputFrame>>,
+}
+
+impl InputPublisher
+{
+    pub fn new() -> InputPublisher
+    {
+        InputPublisher
+        {
+            subscribers: Vec::new(),
+            pending: Vec::new(),
+            pending_engine: Vec::new(),
+        }
+    }
+
+    // clock is the clock of the ticker that will poll the subscriber
+    pub fn subscribe(&mut self, clock: Arc<TickClock>) -> InputSubscriber
+    {
+        let queue = Arc::new(Mutex::new(FrameQueue
+        {
+            frames: VecDeque::new(),
+            overflowed: false,
+        }));
+        self.subscribers.push(Subscription
+        {
+            clock,
+            queue: Arc::downgrade(&queue),
+        });
+
+        return InputSubscriber
+        {
+            queue,
+            latest: None,
+        };
+    }
+
+    pub fn record(&mut self, event: InputEvent)
+    {
+        self.pending.push(event);
+    }
+
+    pub fn recordEngineEvent(&mut self, event: EngineEvent)
+    {
+        self.pending_engine.push(event);
+    }
+
+    // Call once a frame after every event has been handled,
+    // before InputState::endFrame clears the per-frame flags.
+    pub fn publish(&mut self, frame: u64, inState: &InputState)
+    {
+        let published = Arc::new(InputFrame
+        {
+            frame,
+            events: std::mem::replace(&mut self.pending, Vec::new()),
+            engine_events: std::mem::replace(&mut self.pending_engine, Vec::new()),
+            state: inState.clone(),
+        });
+
+        // A subscriber whose thread has stopped gets dropped
+        self.subscribers.retain(|subscriber|
+        {
+            let queue = match subscriber.queue.upgrade()
+            {
+                Some(queue) => queue,
+                None => {return false;},
+            };
+            let mut queue = queue.lock().unwrap();
+
+            if queue.frames.len() >= MAX_QUEUED_FRAMES
+            {
+                queue.frames.pop_front();
+                queue.overflowed = true;
+            }
+            queue.frames.push_back((subscriber.clock.ticks(), published.clone()));
+
+            return true;
+        });
+    }
+}
+
+impl InputSubscriber
+{
+    // Takes every frame meant for tick or earlier, tagged with tick.
+    // Frames meant for later ticks stay queued.
+    pub fn poll(&mut self, tick: u64) -> TickInput
+    {
+        let mut ret = TickInput
+        {
+            tick,
+            frames: None,
+            events: Vec::new(),
+            engine_events: Vec::new(),
+            state: None,
+            overflowed: false,
+        };
+
+        let mut queue = self.queue.lock().unwrap();
+        ret.overflowed = std::mem::replace(&mut queue.overflowed, false);
+        while queue.frames.front().map(|queued| queued.0 <= tick).unwrap_or(false)
+        {
+            let (_, published) = queue.frames.pop_front().unwrap();
+            ret.frames = match ret.frames
+            {
+                Some((first, _)) => Some((first, published.frame)),
+                None => Some((published.frame, published.frame)),
+            };
+            ret.events.extend_from_slice(&published.events);
+            ret.engine_events.extend_from_slice(&published.engine_events);
+            self.latest = Some(published);
+        }
+        ret.state = self.latest.clone();
+
+        return ret;
+    }
+
+    // How many frames are waiting, for ticks that have not run yet
+    pub fn queued(&self) -> usize
+    {
+        return self.queue.lock().unwrap().frames.len();
+    }
+}
+
+impl TickInput
+{
+    pub fn getState(&self) -> Option<&InputState>
+    {
+        return self.state.as_ref().map(|published| &published.state);
+    }
+}
+
+/*************************************/
+// Snapshot tests
+
+#[test]
+fn snapshotOrderTest()
+{
+    use std::time::Duration;
+    use crate::event::{KeyAction, KeyMods};
+    use crate::input::KSK;
+
+    let clock = Arc::new(TickClock::new(Duration::from_millis(50)));
+    let mut publisher = InputPublisher::new();
+    let mut subscriber = publisher.subscribe(clock.clone());
+    let mut inpState = InputState::new();
+
+    clock.setTicks(7);
+
+    let press = InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE);
+    inpState.handleInputEvent(press);
+    publisher.record(press);
+    publisher.publish(0, &inpState);
+
+    let scroll = InputEvent::Scroll(0.0, 1.0);
+    publisher.record(scroll);
+    publisher.recordEngineEvent(EngineEvent::Iconified(true));
+    publisher.publish(1, &inpState);
+
+    // Tick 7 has run by the time frame 2 comes out, so it is for tick 8
+    clock.setTicks(8);
+    publisher.record(InputEvent::CursorEntered(false));
+    publisher.publish(2, &inpState);
+
+    let input = subscriber.poll(7);
+
+    assert_eq!(input.tick, 7);
+    assert_eq!(input.frames, Some((0, 1)));
+    assert_eq!(input.events, vec![press, scroll]);
+    assert_eq!(input.engine_events, vec![EngineEvent::Iconified(true)]);
+    assert_eq!(input.getState().unwrap().isStandardPressed(KSK::W as u64), true);
+    assert_eq!(subscriber.queued(), 1);
+
+    let input = subscriber.poll(8);
+
+    assert_eq!(input.events, vec![InputEvent::CursorEntered(false)]);
+
+    // Nothing new, but the last state is still there
+    let input = subscriber.poll(9);
+
+    assert_eq!(input.events.len(), 0);
+    assert_eq!(input.frames, None);
+    assert_eq!(input.state.unwrap().frame, 2);
+}
+
+#[test]
+fn snapshotThreadTest()
+{
+    use std::thread;
+    use std::time::Duration;
+
+    let clock = Arc::new(TickClock::new(Duration::from_millis(50)));
+    let mut publisher = InputPublisher::new();
+    let mut subscriber = publisher.subscribe(clock);
+    let inpState = InputState::new();
+
+    for frame in 0..10
+    {
+        publisher.record(InputEvent::CursorMoved(frame as f64, 0.0));
+        publisher.publish(frame, &inpState);
+    }
+
+    let seen = thread::spawn(move ||
+    {
+        return subscriber.poll(0).events.len();
+    }).join().unwrap();
+
+    assert_eq!(seen, 10);
+
+    // The subscriber is gone, so publishing just drops it
+    publisher.publish(10, &inpState);
+
+    assert_eq!(publisher.subscribers.len(), 0);
+}
+
+#[test]
+fn snapshotStalledTest()
+{
+    use std::time::Duration;
+
+    let clock = Arc::new(TickClock::new(Duration::from_millis(50)));
+    let mut publisher = InputPublisher::new();
+    let mut subscriber = publisher.subscribe(clock);
+    let inpState = InputState::new();
+
+    // Nobody polls for a long time
+    for frame in 0..1000
+    {
+        publisher.record(InputEvent::CursorMoved(frame as f64, 0.0));
+        publisher.publish(frame, &inpState);
+    }
+
+    assert_eq!(subscriber.queued(), MAX_QUEUED_FRAMES);
+
+    // Only the newest frames are left, and the tick gets told
+    let input = subscriber.poll(0);
+
+    assert_eq!(input.overflowed, true);
+    assert_eq!(input.frames, Some((1000 - MAX_QUEUED_FRAMES as u64, 999)));
+    assert_eq!(input.events.len(), MAX_QUEUED_FRAMES);
+    assert_eq!(input.events[MAX_QUEUED_FRAMES - 1], InputEvent::CursorMoved(999.0, 0.0));
+    assert_eq!(input.state.unwrap().frame, 999);
+
+    publisher.publish(1000, &inpState);
+
+    assert_eq!(subscriber.poll(0).overflowed, false);
+}
+
+/*************************************/
@@ -0,0 +1,274 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use crate::snapshot::TickInput;
+
+// Engine-wide data both tick threads can reach, keyed by type.
+// Lock it only for as long as needed, the other thread is waiting.
+pub struct EngineContext
+{
+    resources: HashMap<TypeId, Box<dyn Any + Send>>,
+}
+
+// What a system gets handed each tick
+pub struct TickContext<'a>
+{
+    pub tick: u64,
+    // The fixed step, in game time
+    pub dt: Duration,
+    // Since the ticker started, in real time and in game time.
+    // They drift apart when paused or time scaled.
+    pub real_time: Duration,
+    pub game_time: Duration,
+    pub input: &'a TickInput,
+    pub engine: &'a Arc<Mutex<EngineContext>>,
+    // For systems that want to add or remove systems, themselves included
+    pub systems: &'a SystemRegistry,
+}
+
+// Anything that can run on a tick thread.
+// Plain closures taking a TickContext work too.
+pub trait Tickable: Send
+{
+    fn tick(&mut self, ctx: &mut TickContext);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SystemId(u64);
+
+enum SystemCommand
+{
+    Add(SystemId, String, i32, Box<dyn Tickable>),
+    Remove(SystemId),
+}
+
+// The handle for adding and removing systems from any thread.
+// Changes take effect at the start of the thread's next tick,
+// so a system can never be removed halfway through running.
+#[derive(Clone)]
+pub struct SystemRegistry
+{
+    sender: Sender<SystemCommand>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct System
+{
+    id: SystemId,
+    name: String,
+    priority: i32,
+    system: Box<dyn Tickable>,
+}
+
+// The systems a tick thread runs, lowest priority number first.
+// Systems with the same priority run in the order they were added.
+pub struct SystemList
+{
+    systems: Vec<System>,
+    receiver: Receiver<SystemCommand>,
+}
+
+impl EngineContext
+{
+    pub fn new() -> EngineContext
+    {
+        EngineContext
+        {
+            resources: HashMap::new()
+        }
+    }
+
+    pub fn insert<T: Any + Send>(&mut self, resource: T)
+    {
+        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    pub fn get<T: Any + Send>(&self) -> Option<&T>
+    {
+        return self.resources.get(&TypeId::of::<T>()).and_then(|resource| resource.downcast_ref::<T>());
+    }
+
+    pub fn get_mut<T: Any + Send>(&mut self) -> Option<&mut T>
+    {
+        return self.resources.get_mut(&TypeId::of::<T>()).and_then(|resource| resource.downcast_mut::<T>());
+    }
+
+    pub fn remove<T: Any + Send>(&mut self) -> Option<T>
+    {
+        return self.resources.remove(&TypeId::of::<T>()).and_then(|resource| resource.downcast::<T>().ok()).map(|resource| *resource);
+    }
+}
+
+impl<F: FnMut(&mut TickContext) + Send> Tickable for F
+{
+    fn tick(&mut self, ctx: &mut TickContext)
+    {
+        self(ctx);
+    }
+}
+
+impl SystemRegistry
+{
+    pub fn add<T: Tickable + 'static>(&self, name: &str, priority: i32, system: T) -> SystemId
+    {
+        let id = SystemId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        // Nothing to do if the thread has already stopped
+        let _ = self.sender.send(SystemCommand::Add(id, name.to_string(), priority, Box::new(system)));
+        return id;
+    }
+
+    pub fn remove(&self, id: SystemId)
+    {
+        let _ = self.sender.send(SystemCommand::Remove(id));
+    }
+}
+
+impl SystemList
+{
+    // The list goes to the tick thread, the registry stays behind
+    pub fn new() -> (SystemList, SystemRegistry)
+    {
+        let (sender, receiver) = channel();
+
+        let list = SystemList
+        {
+            systems: Vec::new(),
+            receiver,
+        };
+        let registry = SystemRegistry
+        {
+            sender,
+            next_id: Arc::new(AtomicU64::new(0)),
+        };
+
+        return (list, registry);
+    }
+
+    // Applies any adds and removes sent since the last call
+    pub fn update(&mut self)
+    {
+        for command in self.receiver.try_iter()
+        {
+            match command
+            {
+                SystemCommand::Add(id, name, priority, system) =>
+                {
+                    // After everything with the same priority or lower
+                    let index = self.systems.iter().position(|existing| existing.priority > priority).unwrap_or(self.systems.len());
+                    self.systems.insert(index, System { id, name, priority, system });
+                },
+                SystemCommand::Remove(id) =>
+                {
+                    self.systems.retain(|existing| existing.id != id);
+                },
+            }
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str>
+    {
+        return self.systems.iter().map(|existing| existing.name.as_str()).collect();
+    }
+
+    pub fn len(&self) -> usize
+    {
+        return self.systems.len();
+    }
+
+    // Picks up any changes, then runs every system once
+    pub fn runAll(&mut self, ctx: &mut TickContext)
+    {
+        self.update();
+        for existing in self.systems.iter_mut()
+        {
+            existing.system.tick(ctx);
+        }
+    }
+}
+
+/*************************************/
+// Systems tests
+
+#[cfg(test)]
+fn emptyTickInput() -> TickInput
+{
+    TickInput
+    {
+        tick: 0,
+        frames: None,
+        events: Vec::new(),
+        engine_events: Vec::new(),
+        state: None,
+        overflowed: false,
+    }
+}
+
+#[test]
+fn systemOrderTest()
+{
+    let (mut list, registry) = SystemList::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let input = emptyTickInput();
+
+    engine.lock().unwrap().insert(Vec::<&'static str>::new());
+
+    registry.add("render_prep", 10, |ctx: &mut TickContext| ctx.engine.lock().unwrap().get_mut::<Vec<&'static str>>().unwrap().push("render_prep"));
+    registry.add("ai", 0, |ctx: &mut TickContext| ctx.engine.lock().unwrap().get_mut::<Vec<&'static str>>().unwrap().push("ai"));
+    registry.add("movement", 0, |ctx: &mut TickContext| ctx.engine.lock().unwrap().get_mut::<Vec<&'static str>>().unwrap().push("movement"));
+
+    let mut ctx = TickContext { tick: 0, dt: Duration::from_millis(50), real_time: Duration::from_secs(0), game_time: Duration::from_secs(0), input: &input, engine: &engine, systems: &registry };
+    list.runAll(&mut ctx);
+
+    assert_eq!(list.names(), vec!["ai", "movement", "render_prep"]);
+    assert_eq!(engine.lock().unwrap().get::<Vec<&'static str>>().unwrap(), &vec!["ai", "movement", "render_prep"]);
+}
+
+#[test]
+fn systemRemoveTest()
+{
+    struct Counter
+    {
+        count: u32,
+    }
+
+    impl Tickable for Counter
+    {
+        fn tick(&mut self, ctx: &mut TickContext)
+        {
+            self.count += 1;
+            ctx.engine.lock().unwrap().insert(self.count);
+        }
+    }
+
+    let (mut list, registry) = SystemList::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let input = emptyTickInput();
+
+    let id = registry.add("counter", 0, Counter { count: 0 });
+
+    // Removes itself on its first tick, after the counter has run.
+    // It only learns its own id once add has returned it.
+    let oneShotId = Arc::new(Mutex::new(None));
+    let ownId = oneShotId.clone();
+    let added = registry.add("one_shot", 1, move |ctx: &mut TickContext| ctx.systems.remove(ownId.lock().unwrap().unwrap()));
+    *oneShotId.lock().unwrap() = Some(added);
+
+    let mut ctx = TickContext { tick: 0, dt: Duration::from_millis(50), real_time: Duration::from_secs(0), game_time: Duration::from_secs(0), input: &input, engine: &engine, systems: &registry };
+    list.runAll(&mut ctx);
+    list.runAll(&mut ctx);
+
+    assert_eq!(list.len(), 1);
+    assert_eq!(engine.lock().unwrap().get::<u32>(), Some(&2));
+
+    registry.remove(id);
+    list.update();
+
+    assert_eq!(list.len(), 0);
+    assert_eq!(engine.lock().unwrap().remove::<u32>(), Some(2));
+}
+
+/*************************************/
@@ -0,0 +1,281 @@
+use crate::event::{InputEvent, KeyAction, KeyMods};
+use crate::input::KSK;
+
+// Somewhere text can be copied to and pasted from.
+// The window is the real one, tests use a plain String.
+pub trait Clipboard
+{
+    fn getClipboard(&self) -> Option<String>;
+    fn setClipboard(&mut self, text: &str);
+}
+
+// A single line of typed text, for names, chat and the console.
+// Characters come from Char events so they follow the keyboard
+// layout and IME, editing is done with Key events. Nothing happens
+// until text entry is turned on with enable.
+pub struct TextInput
+{
+    pub buffer: String,
+    // Measured in chars, not bytes
+    pub cursor: usize,
+    pub enabled: bool,
+    pub max_length: Option<usize>,
+    // Set when Enter is pressed, see takeSubmitted
+    submitted: Option<String>,
+}
+
+impl Clipboard for glfw::Window
+{
+    fn getClipboard(&self) -> Option<String>
+    {
+        return self.get_clipboard_string();
+    }
+
+    fn setClipboard(&mut self, text: &str)
+    {
+        self.set_clipboard_string(text);
+    }
+}
+
+impl Clipboard for String
+{
+    fn getClipboard(&self) -> Option<String>
+    {
+        return Some(self.clone());
+    }
+
+    fn setClipboard(&mut self, text: &str)
+    {
+        *self = text.to_string();
+    }
+}
+
+impl TextInput
+{
+    pub fn new() -> TextInput
+    {
+        TextInput
+        {
+            buffer: String::new(),
+            cursor: 0,
+            enabled: false,
+            max_length: None,
+            submitted: None,
+        }
+    }
+
+    pub fn enable(&mut self)
+    {
+        self.enabled = true;
+    }
+
+    pub fn disable(&mut self)
+    {
+        self.enabled = false;
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.buffer.clear();
+        self.cursor = 0;
+    }
+
+    // What was in the buffer when Enter was last pressed.
+    // The buffer is cleared for the next line.
+    pub fn takeSubmitted(&mut self) -> Option<String>
+    {
+        return self.submitted.take();
+    }
+
+    fn byteIndex(&self, charIndex: usize) -> usize
+    {
+        return self.buffer.char_indices().nth(charIndex).map(|(index, _)| index).unwrap_or(self.buffer.len());
+    }
+
+    fn charCount(&self) -> usize
+    {
+        return self.buffer.chars().count();
+    }
+
+    // Inserts at the cursor, stopping once max_length is reached
+    pub fn insert(&mut self, text: &str)
+    {
+        for c in text.chars()
+        {
+            if c.is_control()
+            {
+                continue;
+            }
+            if self.max_length.map(|max| self.charCount() >= max).unwrap_or(false)
+            {
+                return;
+            }
+            let index = self.byteIndex(self.cursor);
+            self.buffer.insert(index, c);
+            self.cursor += 1;
+        }
+    }
+
+    // Returns true if the event was used for typing
+    pub fn handleInputEvent<C: Clipboard>(&mut self, event: InputEvent, clipboard: &mut C) -> bool
+    {
+        if !self.enabled
+        {
+            return false;
+        }
+
+        return match event
+        {
+            InputEvent::Char(c, _) =>
+            {
+                let mut utf8 = [0u8; 4];
+                self.insert(c.encode_utf8(&mut utf8));
+                true
+            },
+            // Editing keys work while held down, the OS repeat
+            // events come through as Repeat
+            InputEvent::Key(key, KeyAction::Press, mods) | InputEvent::Key(key, KeyAction::Repeat, mods) =>
+                self.editKey(key, mods, clipboard),
+            InputEvent::Key(_, KeyAction::Release, _) => true,
+            _ => false,
+        };
+    }
+
+    fn editKey<C: Clipboard>(&mut self, key: KSK, mods: KeyMods, clipboard: &mut C) -> bool
+    {
+        let length = self.charCount();
+
+        match key
+        {
+            KSK::BACKSPACE =>
+            {
+                if self.cursor > 0
+                {
+                    self.cursor -= 1;
+                    let index = self.byteIndex(self.cursor);
+                    self.buffer.remove(index);
+                }
+            },
+            KSK::DELETE =>
+            {
+                if self.cursor < length
+                {
+                    let index = self.byteIndex(self.cursor);
+                    self.buffer.remove(index);
+                }
+            },
+            KSK::LEFT =>    {if self.cursor > 0 { self.cursor -= 1; }},
+            KSK::RIGHT =>   {if self.cursor < length { self.cursor += 1; }},
+            KSK::HOME =>    {self.cursor = 0;},
+            KSK::END =>     {self.cursor = length;},
+            KSK::ENTER | KSK::NUMPAD_ENTER =>
+            {
+                self.submitted = Some(self.buffer.clone());
+                self.clear();
+            },
+            KSK::C if mods.matches(KeyMods::CONTROL) =>   {clipboard.setClipboard(&self.buffer);},
+            KSK::X if mods.matches(KeyMods::CONTROL) =>
+            {
+                clipboard.setClipboard(&self.buffer);
+                self.clear();
+            },
+            KSK::V if mods.matches(KeyMods::CONTROL) =>
+            {
+                if let Some(text) = clipboard.getClipboard()
+                {
+                    self.insert(&text);
+                }
+            },
+            // Keys that type something are typed through Char events,
+            // anything else, like Escape or Tab, is left for the game
+            _ => {return isTypingKey(key);},
+        }
+
+        return true;
+    }
+}
+
+// Letters, digits, punctuation, space and the numpad keys
+// that send a Char, whatever the layout puts on them
+fn isTypingKey(key: KSK) -> bool
+{
+    let index = key as u64;
+    return index <= KSK::WORLD_TWO as u64
+        || (index >= KSK::NUMPAD_DECIMAL as u64 && index <= KSK::NUMPAD_ADD as u64)
+        || key == KSK::NUMPAD_EQUAL;
+}
+
+/*************************************/
+// Text tests
+
+#[test]
+fn textTypingTest()
+{
+    let mut text = TextInput::new();
+    let mut clipboard = String::new();
+
+    assert_eq!(text.handleInputEvent(InputEvent::Char('a', KeyMods::NONE), &mut clipboard), false);
+
+    text.enable();
+    for c in "héllo".chars()
+    {
+        text.handleInputEvent(InputEvent::Char(c, KeyMods::NONE), &mut clipboard);
+    }
+    text.handleInputEvent(InputEvent::Key(KSK::LEFT, KeyAction::Press, KeyMods::NONE), &mut clipboard);
+    text.handleInputEvent(InputEvent::Key(KSK::BACKSPACE, KeyAction::Press, KeyMods::NONE), &mut clipboard);
+    text.handleInputEvent(InputEvent::Key(KSK::BACKSPACE, KeyAction::Repeat, KeyMods::NONE), &mut clipboard);
+
+    assert_eq!(text.buffer, "héo");
+    assert_eq!(text.cursor, 2);
+}
+
+#[test]
+fn textSubmitTest()
+{
+    let mut text = TextInput::new();
+    let mut clipboard = String::new();
+
+    text.enable();
+    text.insert("gg");
+    text.handleInputEvent(InputEvent::Key(KSK::ENTER, KeyAction::Press, KeyMods::NONE), &mut clipboard);
+
+    assert_eq!(text.takeSubmitted(), Some("gg".to_string()));
+    assert_eq!(text.takeSubmitted(), None);
+    assert_eq!(text.buffer, "");
+}
+
+#[test]
+fn textClipboardTest()
+{
+    let mut text = TextInput::new();
+    let mut clipboard = "pasted".to_string();
+
+    text.enable();
+    text.max_length = Some(4);
+    text.handleInputEvent(InputEvent::Key(KSK::V, KeyAction::Press, KeyMods::CONTROL), &mut clipboard);
+
+    assert_eq!(text.buffer, "past");
+
+    text.handleInputEvent(InputEvent::Key(KSK::X, KeyAction::Press, KeyMods::CONTROL), &mut clipboard);
+
+    assert_eq!(clipboard, "past");
+    assert_eq!(text.buffer, "");
+}
+
+#[test]
+fn textUnusedKeyTest()
+{
+    let mut text = TextInput::new();
+    let mut clipboard = String::new();
+
+    text.enable();
+
+    // Escape still has to close the chat box
+    assert_eq!(text.handleInputEvent(InputEvent::Key(KSK::ESCAPE, KeyAction::Press, KeyMods::NONE), &mut clipboard), false);
+    assert_eq!(text.handleInputEvent(InputEvent::Key(KSK::F1, KeyAction::Press, KeyMods::NONE), &mut clipboard), false);
+    assert_eq!(text.handleInputEvent(InputEvent::Key(KSK::TAB, KeyAction::Press, KeyMods::NONE), &mut clipboard), false);
+    assert_eq!(text.handleInputEvent(InputEvent::Key(KSK::W, KeyAction::Press, KeyMods::NONE), &mut clipboard), true);
+    assert_eq!(text.handleInputEvent(InputEvent::Key(KSK::NUMPAD_ADD, KeyAction::Press, KeyMods::NONE), &mut clipboard), true);
+}
+
+/*************************************/
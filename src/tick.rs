@@ -1,33 +1,128 @@
-use std::thread;
+use std::any::Any;
+use std::fmt;
+use std::thread::{self, JoinHandle};
 use std::time::{Instant, Duration};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+
+use crate::snapshot::InputSubscriber;
+use crate::systems::{EngineContext, SystemList, SystemRegistry, TickContext};
 
 
 
 ////////////////////////////////////////////////
 // Important consts
 
-// Updates per second
-const PHYS_TICK: u64 = 20;
-const TICK_TIME: u64 = 40;
+// Default updates per second
+pub const PHYS_TICK: u32 = 20;
+pub const ENGINE_TICK: u32 = 40;
+
+// Most steps a ticker runs to catch up in one go. Any more
+// and a slow step makes the next wake-up slower still.
+const MAX_TICK_STEPS: u32 = 5;
+
+// Smallest time scale allowed, pausing is how to stop time
+const MIN_TIME_SCALE: f64 = 0.01;
+// Largest time scale allowed. Any faster and even a ticker that
+// keeps up falls further behind than it can catch up on.
+const MAX_TIME_SCALE: f64 = MAX_TICK_STEPS as f64;
 
-const ONE_SECOND_IN_MILLISECONDS: u64 = 1000;
+// How long stop waits for a thread to finish its tick
+const STOP_TIMEOUT_MILLISECONDS: u64 = 2000;
 ////////////////////////////////////////////////
 
 ////////////////////////////////////////////////
 // ticking structs
-// These structs contain shared atomic bools that
-// can be changed by the main thread in order to stop them
-// at any time.
-pub struct tickPhysics
+
+// Where a ticker's ticks run. Thread gets a thread of its own,
+// Inline runs them on whoever calls Ticker::update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TickMode
+{
+    Thread,
+    Inline,
+}
+
+// Runs its systems at a fixed rate, as many as are wanted, e.g.
+// physics at 20 Hz, AI at 10 Hz and networking at 30 Hz.
+// do_stop is a shared atomic bool that can be changed by the
+// main thread in order to stop it at any time. Systems can be
+// added through systems before or after start.
+pub struct Ticker
+{
+    pub name: String,
+    pub mode: TickMode,
+    pub do_stop: Arc<AtomicBool>,
+    pub clock: Arc<TickClock>,
+    pub systems: SystemRegistry,
+    pub stop_timeout: Duration,
+    system_list: Option<SystemList>,
+    // Only kept here for Inline, a thread takes it with it
+    runner: Option<TickRunner>,
+    thread: Option<JoinHandle<()>>,
+    // Disconnects when the thread ends, panicking or not
+    finished: Option<Receiver<()>>,
+    engine: Option<Arc<Mutex<EngineContext>>>,
+    shutdown_hooks: Vec<Box<dyn FnOnce(&mut EngineContext) + Send>>,
+}
+
+#[derive(Debug)]
+pub enum StopError
 {
-    pub do_stop: Arc<AtomicBool>
+    // The thread was still going after stop_timeout, see Ticker::stop
+    TimedOut(String),
+    // A system panicked, the payload is what it panicked with
+    Panicked(String, Box<dyn Any + Send>),
 }
 
-pub struct tickEngine
+// Runs a fixed number of steps per second no matter how long the
+// thread sleeps or how long each step takes. Real time goes into
+// the accumulator, and whole steps come out of it.
+pub struct FixedTimestep
 {
-    pub do_stop: Arc<AtomicBool>
+    pub step: Duration,
+    pub max_steps: u32,
+    accumulator: Duration,
+    last: Option<Instant>,
+    // How much time the steps taken so far add up to,
+    // plus any time dropped by the max_steps cap
+    sim_time: Duration,
+}
+
+// Shared between a ticker and whoever draws its results.
+// The ticked state is always a little behind real time, alpha is
+// how far between the last step and the next one real time is,
+// for interpolating between the last two states.
+// The step and the debug controls live here so they can be
+// changed while running. Game time is real time times the time
+// scale, except while paused, when only requested steps run.
+pub struct TickClock
+{
+    pub start: Instant,
+    step_nanos: AtomicU64,
+    ticks: AtomicU64,
+    // timestep.alpha() when last published, and when that was
+    alpha_bits: AtomicU32,
+    published_nanos: AtomicU64,
+    paused: AtomicBool,
+    pending_steps: AtomicU32,
+    scale_bits: AtomicU64,
+}
+
+// Everything a running ticker needs, on whichever thread runs it
+struct TickRunner
+{
+    timestep: FixedTimestep,
+    tick: u64,
+    last_real: Instant,
+    // Added up from the steps run, so pausing and scaling stop it
+    game_time: Duration,
+    clock: Arc<TickClock>,
+    systems: SystemList,
+    registry: SystemRegistry,
+    input: InputSubscriber,
+    engine: Arc<Mutex<EngineContext>>,
 }
 
 ////////////////////////////////////////////////
@@ -35,80 +130,804 @@ pub struct tickEngine
 ////////////////////////////////////////////////
 // Implementations
 
-impl tickPhysics
+impl FixedTimestep
 {
-    pub fn new() -> tickPhysics
+    pub fn new(step: Duration, maxSteps: u32) -> FixedTimestep
     {
-        tickPhysics
+        FixedTimestep
         {
-            do_stop: Arc::new(AtomicBool::new(false))
+            step,
+            max_steps: maxSteps,
+            accumulator: Duration::from_secs(0),
+            last: None,
+            sim_time: Duration::from_secs(0),
         }
     }
 
-    pub fn stop(&mut self)
+    // Adds the time since the last call and returns how many steps
+    // to run now. Past max_steps the extra time is thrown away, so
+    // the simulation slows down instead of falling further behind.
+    pub fn advance(&mut self, now: Instant) -> u32
     {
-        self.do_stop.store(true, Ordering::Relaxed);
+        let elapsed = match self.last
+        {
+            Some(last) => now.duration_since(last),
+            None => Duration::from_secs(0),
+        };
+        self.last = Some(now);
+
+        return self.advanceBy(elapsed);
     }
 
-    pub fn start(&mut self)
+    // The same as advance, for time that is not real time,
+    // like real time run through a time scale
+    pub fn advanceBy(&mut self, elapsed: Duration) -> u32
     {
-        let l_stop = self.do_stop.clone();
-        
-        let phys = thread::spawn(move ||
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step
         {
-            let mut oldNow = Instant::now();
-            loop
+            self.accumulator -= self.step;
+            self.sim_time += self.step;
+            steps += 1;
+
+            // A max_steps of 0 still runs one, rather than no cap at all
+            if steps >= self.max_steps.max(1)
             {
-                if l_stop.load(Ordering::Relaxed)
-                {
-                    println!("Shutting Down Phsyics");
-                    break;
-                }
-                thread::sleep(Duration::from_millis(ONE_SECOND_IN_MILLISECONDS/PHYS_TICK));
-                let t = Instant::now().duration_since(oldNow);
-                // println!("Physics tick: {:?}", t);
-                oldNow = Instant::now();
+                // Keep only the part of a step that was left over
+                let leftover = Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+                self.sim_time += self.accumulator - leftover;
+                self.accumulator = leftover;
+                break;
+            }
+        }
+
+        return steps;
+    }
+
+    // Between 0 and 1, how far real time is past the last step
+    pub fn alpha(&self) -> f32
+    {
+        return self.accumulator.as_secs_f32() / self.step.as_secs_f32();
+    }
+
+    // One step that no time was added for, for single-stepping
+    pub fn forceStep(&mut self)
+    {
+        self.sim_time += self.step;
+    }
+
+    pub fn untilNextStep(&self) -> Duration
+    {
+        return self.step.saturating_sub(self.accumulator);
+    }
+
+    pub fn simTime(&self) -> Duration
+    {
+        return self.sim_time;
+    }
+}
+
+fn stepForRate(hz: u32) -> Duration
+{
+    return Duration::from_secs(1) / hz.max(1);
+}
+
+// The message from a panic!, when it had one
+pub fn panicMessage(payload: &Box<dyn Any + Send>) -> String
+{
+    if let Some(message) = payload.downcast_ref::<&str>()
+    {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>()
+    {
+        return message.clone();
+    }
+    return "unknown panic".to_string();
+}
+
+impl fmt::Display for StopError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        return match self
+        {
+            StopError::TimedOut(name) =>            write!(f, "{} ticker did not stop in time", name),
+            StopError::Panicked(name, payload) =>   write!(f, "{} ticker panicked: {}", name, panicMessage(payload)),
+        };
+    }
+}
+
+impl TickClock
+{
+    pub fn new(step: Duration) -> TickClock
+    {
+        TickClock
+        {
+            start: Instant::now(),
+            step_nanos: AtomicU64::new(step.as_nanos() as u64),
+            ticks: AtomicU64::new(0),
+            alpha_bits: AtomicU32::new(0),
+            published_nanos: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            pending_steps: AtomicU32::new(0),
+            scale_bits: AtomicU64::new(1.0f64.to_bits()),
+        }
+    }
+
+    pub fn step(&self) -> Duration
+    {
+        return Duration::from_nanos(self.step_nanos.load(Ordering::Acquire));
+    }
+
+    pub fn setStep(&self, step: Duration)
+    {
+        self.step_nanos.store(step.as_nanos() as u64, Ordering::Release);
+    }
+
+    pub fn isPaused(&self) -> bool
+    {
+        return self.paused.load(Ordering::Acquire);
+    }
+
+    pub fn setPaused(&self, paused: bool)
+    {
+        self.paused.store(paused, Ordering::Release);
+    }
+
+    // Asks for steps more ticks to run, only used while paused
+    pub fn requestSteps(&self, steps: u32)
+    {
+        self.pending_steps.fetch_add(steps, Ordering::AcqRel);
+    }
+
+    // Steps requested since the last call
+    pub fn takeSteps(&self) -> u32
+    {
+        return self.pending_steps.swap(0, Ordering::AcqRel);
+    }
+
+    pub fn timeScale(&self) -> f64
+    {
+        return f64::from_bits(self.scale_bits.load(Ordering::Acquire));
+    }
+
+    // 1.0 is real time. Use setPaused to stop time rather than 0.
+    // A scale that is not a number at all leaves the old one in place.
+    pub fn setTimeScale(&self, scale: f64)
+    {
+        if !scale.is_finite()
+        {
+            return;
+        }
+        self.scale_bits.store(scale.max(MIN_TIME_SCALE).min(MAX_TIME_SCALE).to_bits(), Ordering::Release);
+    }
+
+    // Called by the ticker after it steps
+    pub fn publish(&self, timestep: &FixedTimestep, ticks: u64, now: Instant)
+    {
+        self.alpha_bits.store(timestep.alpha().to_bits(), Ordering::Release);
+        self.published_nanos.store(now.duration_since(self.start).as_nanos() as u64, Ordering::Release);
+        self.setTicks(ticks);
+    }
+
+    pub fn ticks(&self) -> u64
+    {
+        return self.ticks.load(Ordering::Acquire);
+    }
+
+    pub fn setTicks(&self, ticks: u64)
+    {
+        self.ticks.store(ticks, Ordering::Release);
+    }
+
+    // The interpolation alpha at now, for the renderer.
+    // Frozen while paused, so a paused game holds still.
+    pub fn alpha(&self, now: Instant) -> f32
+    {
+        let alpha = f32::from_bits(self.alpha_bits.load(Ordering::Acquire));
+        if self.isPaused()
+        {
+            return alpha;
+        }
+
+        let published = Duration::from_nanos(self.published_nanos.load(Ordering::Acquire));
+        let since = now.duration_since(self.start).checked_sub(published).unwrap_or(Duration::from_secs(0));
+        return (alpha + (since.as_secs_f64() * self.timeScale() / self.step().as_secs_f64()) as f32).min(1.0);
+    }
+}
+
+impl TickRunner
+{
+    // Runs every tick that is due by now and returns how many
+    fn runDue(&mut self, now: Instant) -> u32
+    {
+        // Pick up any change to the rate
+        self.timestep.step = self.clock.step();
+
+        let real = now.duration_since(self.last_real);
+        self.last_real = now;
+
+        let requested = self.clock.takeSteps();
+        let steps = if self.clock.isPaused()
+        {
+            if requested == 0
+            {
+                // Nothing is going to tick, so input is thrown away as it
+                // comes in rather than piling up for the first tick after
+                self.input.poll(self.tick);
+            }
+            for _ in 0..requested
+            {
+                self.timestep.forceStep();
             }
-        });
+            requested
+        }
+        else
+        {
+            self.timestep.advanceBy(real.mul_f64(self.clock.timeScale()))
+        };
+
+        for _ in 0..steps
+        {
+            let tickInput = self.input.poll(self.tick);
+            let mut ctx = TickContext
+            {
+                tick: self.tick,
+                dt: self.timestep.step,
+                real_time: now.duration_since(self.clock.start),
+                game_time: self.game_time,
+                input: &tickInput,
+                engine: &self.engine,
+                systems: &self.registry,
+            };
+            self.systems.runAll(&mut ctx);
+            self.tick += 1;
+            self.game_time += self.timestep.step;
+        }
+        self.clock.publish(&self.timestep, self.tick, now);
+
+        return steps;
+    }
+
+    // How long to sleep in real time before the next tick is due
+    fn untilNextTick(&self) -> Duration
+    {
+        if self.clock.isPaused()
+        {
+            // Woken early by step and resume
+            return self.clock.step();
+        }
+        return self.timestep.untilNextStep().div_f64(self.clock.timeScale());
     }
 }
 
-impl tickEngine
+impl Ticker
 {
-    pub fn new() -> tickEngine
+    // hz is ticks per second
+    pub fn new(name: &str, hz: u32, mode: TickMode) -> Ticker
+    {
+        let (systemList, systems) = SystemList::new();
+
+        Ticker
+        {
+            name: name.to_string(),
+            mode,
+            do_stop: Arc::new(AtomicBool::new(false)),
+            clock: Arc::new(TickClock::new(stepForRate(hz))),
+            systems,
+            stop_timeout: Duration::from_millis(STOP_TIMEOUT_MILLISECONDS),
+            system_list: Some(systemList),
+            runner: None,
+            thread: None,
+            finished: None,
+            engine: None,
+            shutdown_hooks: Vec::new(),
+        }
+    }
+
+    // Takes effect from the next tick, even while running
+    pub fn setRate(&self, hz: u32)
+    {
+        self.clock.setStep(stepForRate(hz));
+    }
+
+    pub fn rate(&self) -> f64
+    {
+        return 1.0 / self.clock.step().as_secs_f64();
+    }
+
+    ////////////////////////////////////////////////
+    // Debug controls
+    // The step stays fixed through all of these, only how
+    // often steps come changes.
+
+    // Freezes ticking until resume, apart from requested steps
+    pub fn pause(&self)
+    {
+        self.clock.setPaused(true);
+    }
+
+    // Carries on from where it paused, the paused time is skipped
+    pub fn resume(&self)
+    {
+        self.clock.setPaused(false);
+        self.wake();
+    }
+
+    pub fn isPaused(&self) -> bool
+    {
+        return self.clock.isPaused();
+    }
+
+    // While paused, runs exactly steps more ticks
+    pub fn step(&self, steps: u32)
+    {
+        self.clock.requestSteps(steps);
+        self.wake();
+    }
+
+    // 0.25 is quarter speed, 4.0 four times as fast
+    pub fn setTimeScale(&self, scale: f64)
+    {
+        self.clock.setTimeScale(scale);
+        self.wake();
+    }
+
+    pub fn timeScale(&self) -> f64
+    {
+        return self.clock.timeScale();
+    }
+
+    // So a sleeping thread sees a change now rather than next tick
+    fn wake(&self)
     {
-        tickEngine
+        if let Some(handle) = self.thread.as_ref()
         {
-            do_stop: Arc::new(AtomicBool::new(false))
+            handle.thread().unpark();
         }
     }
+    ////////////////////////////////////////////////
+
+    // Runs on the stopping thread once the ticker has stopped, in the
+    // order they were added. They still run if a system panicked.
+    pub fn onShutdown<F: FnOnce(&mut EngineContext) + Send + 'static>(&mut self, hook: F)
+    {
+        self.shutdown_hooks.push(Box::new(hook));
+    }
 
-    pub fn stop(&mut self)
+    pub fn isRunning(&self) -> bool
+    {
+        return match self.thread.as_ref()
+        {
+            Some(handle) => !handle.is_finished(),
+            None => self.runner.is_some(),
+        };
+    }
+
+    // Wakes the thread, waits up to stop_timeout for it to finish its
+    // tick, then runs the shutdown hooks. Stopping twice does nothing.
+    // On TimedOut the hooks are not run, the stuck thread may still be
+    // holding the engine lock or about to use what they clean up.
+    // The ticker keeps the thread, so calling stop again waits again.
+    pub fn stop(&mut self) -> Result<(), StopError>
     {
         self.do_stop.store(true, Ordering::Relaxed);
+        self.runner = None;
+
+        let mut ret = Ok(());
+        if let Some(handle) = self.thread.take()
+        {
+            handle.thread().unpark();
+            let finished = self.finished.take().unwrap();
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(self.stop_timeout)
+            {
+                self.thread = Some(handle);
+                self.finished = Some(finished);
+                return Err(StopError::TimedOut(self.name.clone()));
+            }
+            ret = match handle.join()
+            {
+                Ok(()) => Ok(()),
+                Err(payload) => Err(StopError::Panicked(self.name.clone(), payload)),
+            };
+        }
+
+        if let Some(engine) = self.engine.take()
+        {
+            // A panicking system may have been holding the lock,
+            // the hooks still get their chance to clean up
+            let mut engine = engine.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for hook in self.shutdown_hooks.drain(..)
+            {
+                hook(&mut engine);
+            }
+        }
+
+        return ret;
     }
 
-    pub fn start(&mut self)
+    // Call once a frame from the thread that owns the ticker. If its
+    // thread died from a panic, panics here with the same payload.
+    pub fn propagatePanic(&mut self)
     {
-        let l_stop = self.do_stop.clone();
-        
-        let eng = thread::spawn(move ||
+        let died = match self.thread.as_ref()
         {
-            let mut oldNow = Instant::now();
-            loop
+            Some(handle) => handle.is_finished() && !self.do_stop.load(Ordering::Relaxed),
+            None => false,
+        };
+        if died
+        {
+            if let Err(StopError::Panicked(_, payload)) = self.stop()
             {
-                if l_stop.load(Ordering::Relaxed)
-                {
-                    println!("Shutting Down Engine");
-                    break;
-                }
-                thread::sleep(Duration::from_millis(ONE_SECOND_IN_MILLISECONDS/TICK_TIME));
-                let t = Instant::now().duration_since(oldNow);
-                // println!("Engine tick: {:?}", t);
-                oldNow = Instant::now();
+                std::panic::resume_unwind(payload);
             }
-        });
+        }
+    }
+
+    // input hands the ticker what the main thread saw each frame,
+    // engine is shared with the other tickers
+    pub fn start(&mut self, input: InputSubscriber, engine: Arc<Mutex<EngineContext>>)
+    {
+        let systems = self.system_list.take().expect("ticker already started");
+        let mut runner = TickRunner
+        {
+            timestep: FixedTimestep::new(self.clock.step(), MAX_TICK_STEPS),
+            tick: 0,
+            last_real: self.clock.start,
+            game_time: Duration::from_secs(0),
+            clock: self.clock.clone(),
+            systems,
+            registry: self.systems.clone(),
+            input,
+            engine,
+        };
+        self.engine = Some(runner.engine.clone());
+
+        match self.mode
+        {
+            TickMode::Inline =>
+            {
+                self.runner = Some(runner);
+            },
+            TickMode::Thread =>
+            {
+                let l_stop = self.do_stop.clone();
+                let l_name = self.name.clone();
+                let (l_finished, finished) = channel::<()>();
+
+                let handle = thread::Builder::new().name(self.name.clone()).spawn(move ||
+                {
+                    // Dropped on the way out, even when unwinding
+                    let _finished = l_finished;
+                    loop
+                    {
+                        if l_stop.load(Ordering::Relaxed)
+                        {
+                            println!("Shutting Down {}", l_name);
+                            break;
+                        }
+                        runner.runDue(Instant::now());
+                        // Sleep until the next step is due rather than a whole
+                        // step, so time spent stepping does not add up to drift.
+                        // Parked rather than slept so stop can wake it early.
+                        thread::park_timeout(runner.untilNextTick());
+                    }
+                }).expect("could not spawn ticker thread");
+
+                self.thread = Some(handle);
+                self.finished = Some(finished);
+            },
+        }
+    }
+
+    // For Inline tickers, call once a frame. Runs the ticks due by now
+    // and returns how many. Thread tickers run themselves, so this
+    // does nothing for them.
+    pub fn update(&mut self, now: Instant) -> u32
+    {
+        if self.do_stop.load(Ordering::Relaxed)
+        {
+            return 0;
+        }
+        return match self.runner.as_mut()
+        {
+            Some(runner) => runner.runDue(now),
+            None => 0,
+        };
+    }
+
+    // How far between ticked states to draw, see TickClock
+    pub fn interpolationAlpha(&self) -> f32
+    {
+        return self.clock.alpha(Instant::now());
+    }
+}
+
+////////////////////////////////////////////////
+
+/*************************************/
+// Tick tests
+
+#[test]
+fn fixedTimestepTest()
+{
+    let now = Instant::now();
+    let mut timestep = FixedTimestep::new(Duration::from_millis(50), 5);
+
+    assert_eq!(timestep.advance(now), 0);
+    assert_eq!(timestep.advance(now + Duration::from_millis(120)), 2);
+    assert!((timestep.alpha() - 0.4).abs() < 0.0001);
+    assert_eq!(timestep.untilNextStep(), Duration::from_millis(30));
+
+    // Jitter still adds up to the right number of steps
+    assert_eq!(timestep.advance(now + Duration::from_millis(149)), 0);
+    assert_eq!(timestep.advance(now + Duration::from_millis(151)), 1);
+    assert_eq!(timestep.simTime(), Duration::from_millis(150));
+}
+
+#[test]
+fn fixedTimestepCapTest()
+{
+    let now = Instant::now();
+    let mut timestep = FixedTimestep::new(Duration::from_millis(50), 5);
+
+    timestep.advance(now);
+
+    // A two second hitch only runs five steps and keeps the remainder
+    assert_eq!(timestep.advance(now + Duration::from_millis(2010)), 5);
+    assert!((timestep.alpha() - 0.2).abs() < 0.0001);
+    assert_eq!(timestep.advance(now + Duration::from_millis(2050)), 1);
+
+    timestep.max_steps = 0;
+
+    assert_eq!(timestep.advance(now + Duration::from_millis(4050)), 1);
+}
+
+#[test]
+fn timeScaleTest()
+{
+    let clock = TickClock::new(Duration::from_millis(50));
+
+    clock.setTimeScale(f64::NAN);
+    clock.setTimeScale(f64::INFINITY);
+
+    assert_eq!(clock.timeScale(), 1.0);
+
+    clock.setTimeScale(1000.0);
+
+    assert_eq!(clock.timeScale(), MAX_TIME_SCALE);
+
+    clock.setTimeScale(0.0);
+
+    assert_eq!(clock.timeScale(), MIN_TIME_SCALE);
+
+    // Left over from a longer step when the rate goes up
+    let mut timestep = FixedTimestep::new(Duration::from_millis(50), 5);
+    timestep.accumulator = Duration::from_millis(40);
+    timestep.step = Duration::from_millis(20);
+
+    assert_eq!(timestep.untilNextStep(), Duration::from_millis(0));
+}
+
+#[test]
+fn inlineTickerTest()
+{
+    use crate::snapshot::InputPublisher;
+
+    let mut publisher = InputPublisher::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let mut ai = Ticker::new("AI", 20, TickMode::Inline);
+    let start = ai.clock.start;
+
+    ai.systems.add("count", 0, |ctx: &mut TickContext|
+    {
+        let mut engine = ctx.engine.lock().unwrap();
+        let dts = engine.get_mut::<Vec<Duration>>().unwrap();
+        dts.push(ctx.dt);
+    });
+    engine.lock().unwrap().insert(Vec::<Duration>::new());
+    ai.start(publisher.subscribe(ai.clock.clone()), engine.clone());
+
+    assert_eq!(ai.update(start + Duration::from_millis(120)), 2);
+
+    // Slower from the next tick on, the 20ms left over carries across
+    ai.setRate(10);
+
+    assert_eq!(ai.update(start + Duration::from_millis(320)), 2);
+    assert_eq!(ai.clock.ticks(), 4);
+    assert_eq!(engine.lock().unwrap().get::<Vec<Duration>>().unwrap(), &vec![
+        Duration::from_millis(50), Duration::from_millis(50),
+        Duration::from_millis(100), Duration::from_millis(100),
+    ]);
+
+    ai.stop().unwrap();
+
+    assert_eq!(ai.update(start + Duration::from_millis(1000)), 0);
+}
+
+#[test]
+fn tickerPauseTest()
+{
+    use crate::snapshot::InputPublisher;
+
+    let mut publisher = InputPublisher::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let mut physics = Ticker::new("Physics", 20, TickMode::Inline);
+    let start = physics.clock.start;
+
+    physics.systems.add("times", 0, |ctx: &mut TickContext|
+    {
+        let (realTime, gameTime) = (ctx.real_time, ctx.game_time);
+        ctx.engine.lock().unwrap().get_mut::<Vec<(Duration, Duration)>>().unwrap().push((realTime, gameTime));
+    });
+    engine.lock().unwrap().insert(Vec::<(Duration, Duration)>::new());
+    physics.start(publisher.subscribe(physics.clock.clone()), engine.clone());
+
+    physics.pause();
+
+    assert_eq!(physics.update(start + Duration::from_millis(500)), 0);
+
+    physics.step(2);
+
+    assert_eq!(physics.update(start + Duration::from_millis(510)), 2);
+    assert_eq!(physics.update(start + Duration::from_millis(520)), 0);
+
+    // Quarter speed, 200ms of real time is one 50ms step
+    physics.resume();
+    physics.setTimeScale(0.25);
+
+    assert_eq!(physics.update(start + Duration::from_millis(720)), 1);
+
+    physics.setTimeScale(4.0);
+
+    assert_eq!(physics.update(start + Duration::from_millis(770)), 4);
+    assert_eq!(engine.lock().unwrap().get::<Vec<(Duration, Duration)>>().unwrap()[..3], [
+        (Duration::from_millis(510), Duration::from_millis(0)),
+        (Duration::from_millis(510), Duration::from_millis(50)),
+        (Duration::from_millis(720), Duration::from_millis(100)),
+    ]);
+}
+
+#[test]
+fn tickerPausedInputTest()
+{
+    use crate::event::InputEvent;
+    use crate::input::InputState;
+    use crate::snapshot::InputPublisher;
+
+    let mut publisher = InputPublisher::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let mut physics = Ticker::new("Physics", 20, TickMode::Inline);
+    let inpState = InputState::new();
+    let start = physics.clock.start;
+
+    physics.systems.add("events", 0, |ctx: &mut TickContext|
+    {
+        let events = ctx.input.events.clone();
+        ctx.engine.lock().unwrap().get_mut::<Vec<Vec<InputEvent>>>().unwrap().push(events);
+    });
+    engine.lock().unwrap().insert(Vec::<Vec<InputEvent>>::new());
+    physics.start(publisher.subscribe(physics.clock.clone()), engine.clone());
+    physics.pause();
+
+    // A paused ticker keeps its queue empty
+    for frame in 0..100
+    {
+        publisher.record(InputEvent::CursorMoved(frame as f64, 0.0));
+        publisher.publish(frame, &inpState);
+        physics.update(start + Duration::from_millis(frame * 16));
+    }
+
+    assert_eq!(physics.runner.as_ref().unwrap().input.queued(), 0);
+
+    // A step only sees what came in since the last frame
+    publisher.record(InputEvent::Scroll(0.0, 1.0));
+    publisher.publish(100, &inpState);
+    physics.step(1);
+
+    assert_eq!(physics.update(start + Duration::from_millis(1600)), 1);
+    assert_eq!(engine.lock().unwrap().get::<Vec<Vec<InputEvent>>>().unwrap(), &vec![vec![InputEvent::Scroll(0.0, 1.0)]]);
+}
+
+#[test]
+fn tickerStopTest()
+{
+    use crate::snapshot::InputPublisher;
+
+    let mut publisher = InputPublisher::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    // Slow enough that it is asleep when stop is called
+    let mut slow = Ticker::new("Slow", 1, TickMode::Thread);
+
+    engine.lock().unwrap().insert(Vec::<&'static str>::new());
+    slow.onShutdown(|engine| engine.get_mut::<Vec<&'static str>>().unwrap().push("save"));
+    slow.onShutdown(|engine| engine.get_mut::<Vec<&'static str>>().unwrap().push("disconnect"));
+    slow.start(publisher.subscribe(slow.clock.clone()), engine.clone());
+
+    let before = Instant::now();
+    slow.stop().unwrap();
+
+    assert!(Instant::now().duration_since(before) < Duration::from_millis(500));
+    assert_eq!(slow.isRunning(), false);
+    assert_eq!(engine.lock().unwrap().get::<Vec<&'static str>>().unwrap(), &vec!["save", "disconnect"]);
+    assert!(slow.stop().is_ok());
+}
+
+#[test]
+fn tickerStopTimeoutTest()
+{
+    use crate::snapshot::InputPublisher;
+
+    let mut publisher = InputPublisher::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let mut stuck = Ticker::new("Stuck", 1000, TickMode::Thread);
+    let stalling = Arc::new(AtomicBool::new(false));
+
+    stuck.stop_timeout = Duration::from_millis(50);
+    engine.lock().unwrap().insert(Vec::<&'static str>::new());
+    stuck.onShutdown(|engine| engine.get_mut::<Vec<&'static str>>().unwrap().push("hook"));
+
+    // Holds the engine lock well past the timeout
+    let l_stalling = stalling.clone();
+    stuck.systems.add("stall", 0, move |ctx: &mut TickContext|
+    {
+        let _engine = ctx.engine.lock().unwrap();
+        l_stalling.store(true, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(300));
+    });
+    stuck.start(publisher.subscribe(stuck.clock.clone()), engine.clone());
+
+    while !stalling.load(Ordering::Relaxed)
+    {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let before = Instant::now();
+
+    match stuck.stop()
+    {
+        Err(StopError::TimedOut(name)) => assert_eq!(name, "Stuck"),
+        _ => panic!("stop did not time out"),
+    }
+    assert!(Instant::now().duration_since(before) < Duration::from_millis(250));
+
+    // Once the tick finishes, stopping again joins it and runs the hooks
+    stuck.stop_timeout = Duration::from_secs(5);
+
+    assert!(stuck.stop().is_ok());
+    assert_eq!(engine.lock().unwrap().get::<Vec<&'static str>>().unwrap(), &vec!["hook"]);
+}
+
+#[test]
+fn tickerPanicTest()
+{
+    use crate::snapshot::InputPublisher;
+
+    let mut publisher = InputPublisher::new();
+    let engine = Arc::new(Mutex::new(EngineContext::new()));
+    let mut broken = Ticker::new("Broken", 1000, TickMode::Thread);
+
+    broken.systems.add("explode", 0, |_: &mut TickContext| panic!("boom"));
+    broken.start(publisher.subscribe(broken.clock.clone()), engine);
+
+    let before = Instant::now();
+    while broken.isRunning() && Instant::now().duration_since(before) < Duration::from_secs(5)
+    {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    match broken.stop()
+    {
+        Err(StopError::Panicked(name, payload)) =>
+        {
+            assert_eq!(name, "Broken");
+            assert_eq!(panicMessage(&payload), "boom");
+        },
+        _ => panic!("the panic was not passed on"),
     }
 }
 
-////////////////////////////////////////////////
\ No newline at end of file
+/*************************************/